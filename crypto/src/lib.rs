@@ -0,0 +1,98 @@
+//! RSA signature checks for mynachain, as a host function so the runtime doesn't
+//! run RSA-2048 inside Wasm.
+//!
+//! `myna_crypto::rsa_verify_pkcs1_sha256` is the host function. `fallback` is the
+//! same check in plain Rust: it is what the host runs, and what a runtime can run
+//! in Wasm where the host function isn't available.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_runtime_interface::runtime_interface;
+
+pub mod fallback {
+    use rsa::{hash::Hashes, BigUint, PaddingScheme, PublicKey, RSAPublicKey};
+
+    /// Whether `signature` is a PKCS#1 v1.5 signature over a SHA-256 `DigestInfo`
+    /// wrapping `digest`, by the key (`modulus`, `exponent`), both big-endian.
+    /// Anything malformed is simply not a valid signature.
+    pub fn rsa_verify_pkcs1_sha256(
+        modulus: &[u8],
+        exponent: &[u8],
+        digest: &[u8],
+        signature: &[u8],
+    ) -> bool {
+        if digest.len() != 32 {
+            return false;
+        }
+        let key = match RSAPublicKey::new(
+            BigUint::from_bytes_be(modulus),
+            BigUint::from_bytes_be(exponent),
+        ) {
+            Ok(key) => key,
+            Err(_) => return false,
+        };
+        key.verify(PaddingScheme::PKCS1v15, Some(&Hashes::SHA2_256), digest, signature)
+            .is_ok()
+    }
+}
+
+/// Host functions of mynachain nodes
+#[runtime_interface]
+pub trait MynaCrypto {
+    /// See `fallback::rsa_verify_pkcs1_sha256`
+    fn rsa_verify_pkcs1_sha256(
+        modulus: &[u8],
+        exponent: &[u8],
+        digest: &[u8],
+        signature: &[u8],
+    ) -> bool {
+        fallback::rsa_verify_pkcs1_sha256(modulus, exponent, digest, signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use rsa::{hash::Hashes, PaddingScheme, PublicKey, RSAPrivateKey};
+
+    /// Both implementations, which must agree
+    fn verify(modulus: &[u8], exponent: &[u8], digest: &[u8], signature: &[u8]) -> bool {
+        let host = myna_crypto::rsa_verify_pkcs1_sha256(modulus, exponent, digest, signature);
+        let fallback = fallback::rsa_verify_pkcs1_sha256(modulus, exponent, digest, signature);
+        assert_eq!(host, fallback);
+        host
+    }
+
+    #[test]
+    fn host_and_fallback_agree() {
+        let key = RSAPrivateKey::new(&mut StdRng::seed_from_u64(0), 2048).unwrap();
+        let other = RSAPrivateKey::new(&mut StdRng::seed_from_u64(1), 2048).unwrap();
+        let n = key.n().to_bytes_be();
+        let e = key.e().to_bytes_be();
+        let digest = [7u8; 32];
+        let signature = key
+            .sign(PaddingScheme::PKCS1v15, Some(&Hashes::SHA2_256), &digest)
+            .unwrap();
+
+        assert!(verify(&n, &e, &digest, &signature));
+        // leading zeros don't change the key
+        assert!(verify(&[&[0u8][..], &n].concat(), &e, &digest, &signature));
+
+        assert!(!verify(&n, &e, &[8u8; 32], &signature));
+        assert!(!verify(&n, &e, &digest[..31], &signature));
+        assert!(!verify(&n, &e, &digest, &signature[1..]));
+        assert!(!verify(&n, &e, &digest, &[]));
+        assert!(!verify(&other.n().to_bytes_be(), &e, &digest, &signature));
+        assert!(!verify(&n, &[3], &digest, &signature));
+        assert!(!verify(&n, &[], &digest, &signature));
+        assert!(!verify(&[], &e, &digest, &signature));
+
+        let mut flipped = signature.clone();
+        flipped[100] ^= 1;
+        assert!(!verify(&n, &e, &digest, &flipped));
+
+        let no_digest_info = key.sign(PaddingScheme::PKCS1v15, None, &digest).unwrap();
+        assert!(!verify(&n, &e, &digest, &no_digest_info));
+    }
+}
@@ -0,0 +1,39 @@
+//! Keys and certs for the benches, built with the runtime's `der` test utilities.
+
+use node_template_runtime::der;
+use rand::{rngs::StdRng, SeedableRng};
+use rsa::{hash::Hashes, PaddingScheme, RSAPrivateKey};
+
+/// JPKI keys are 2048 bits
+const KEY_BITS: usize = 2048;
+
+pub fn key(seed: u64) -> RSAPrivateKey {
+    RSAPrivateKey::new(&mut StdRng::seed_from_u64(seed), KEY_BITS).unwrap()
+}
+
+/// A cert for `key` signed by `ca`
+pub fn cert(key: &RSAPrivateKey, ca: &RSAPrivateKey) -> Vec<u8> {
+    let name = der::name("Bench CA");
+    let public_key = der::seq(&[der::uint(&key.n().to_bytes_be()), der::uint(&key.e().to_bytes_be())]);
+    let tbs = der::seq(&[
+        der::explicit(0, &der::uint(&[2])),
+        der::uint(&[1]),
+        der::algorithm(der::SHA256_WITH_RSA),
+        name.clone(),
+        der::seq(&[der::utc_time("200101000000Z"), der::utc_time("491231235959Z")]),
+        name,
+        der::seq(&[der::algorithm(der::RSA_ENCRYPTION), der::bit_string(&public_key)]),
+    ]);
+    let signature = ca
+        .sign(
+            PaddingScheme::PKCS1v15,
+            Some(&Hashes::SHA2_256),
+            &sp_io::hashing::sha2_256(&tbs),
+        )
+        .unwrap();
+    der::seq(&[
+        tbs,
+        der::algorithm(der::SHA256_WITH_RSA),
+        der::bit_string(&signature),
+    ])
+}
@@ -0,0 +1,55 @@
+//! Per-tx signature check: parsing the cert on every tx (before) vs. the key cached in `Account` (after).
+//!
+//! `cargo bench -p node-template-runtime --features test-utils --bench signature`
+
+mod common;
+
+use common::cert;
+use criterion::{criterion_group, criterion_main, Criterion};
+use myna::crypto;
+use node_template_runtime::types;
+use rsa::{hash::Hashes, PaddingScheme, PublicKey};
+use sp_core::H256;
+
+fn signature_check(c: &mut Criterion) {
+    let key = common::key(0);
+    let cert = cert(&key, &common::key(1));
+    let cached = types::RsaPublicKey {
+        modulus: key.n().to_bytes_be(),
+        exponent: key.e().to_bytes_be(),
+    };
+
+    let ctx = types::SigningContext {
+        genesis_hash: H256::repeat_byte(1),
+        spec_version: 1,
+    };
+    let tbs = types::Tx::Send(types::TxSend {
+        to: H256::repeat_byte(2),
+        amount: 100,
+        nonce: 0,
+    });
+    let digest = ctx.sighash(&tbs);
+    let tx = types::SignedData {
+        signature: key
+            .sign(PaddingScheme::PKCS1v15, Some(&Hashes::SHA2_256), digest.as_bytes())
+            .unwrap(),
+        tbs,
+        id: H256::zero(),
+    };
+
+    c.bench_function("pubkey: extract from cert", |b| {
+        b.iter(|| crypto::extract_pubkey(&cert[..]).unwrap())
+    });
+    c.bench_function("tx check: before", |b| {
+        b.iter(|| {
+            let pubkey = crypto::extract_pubkey(&cert[..]).unwrap();
+            crypto::verify(pubkey, digest.as_bytes(), &tx.signature[..]).unwrap()
+        })
+    });
+    c.bench_function("tx check: after", |b| {
+        b.iter(|| tx.verify(&cached, &ctx).unwrap())
+    });
+}
+
+criterion_group!(benches, signature_check);
+criterion_main!(benches);
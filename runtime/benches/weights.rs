@@ -0,0 +1,47 @@
+//! The steps `weights` charges for, one by one.
+//!
+//! `cargo bench -p node-template-runtime --features test-utils --bench weights`
+
+mod common;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use node_template_runtime::{types, x509};
+use rsa::{hash::Hashes, PaddingScheme};
+
+fn steps(c: &mut Criterion) {
+    let ca_key = common::key(1);
+    let ca = common::cert(&ca_key, &ca_key);
+    let key = common::key(0);
+    let cert = common::cert(&key, &ca_key);
+    let create = types::TxCreateAccount { cert: cert.clone(), nonce: 0 };
+
+    let digest = [7u8; 32];
+    let signature = key
+        .sign(PaddingScheme::PKCS1v15, Some(&Hashes::SHA2_256), &digest)
+        .unwrap();
+    let pubkey = types::RsaPublicKey {
+        modulus: key.n().to_bytes_be(),
+        exponent: key.e().to_bytes_be(),
+    };
+
+    // RSA_VERIFY_WEIGHT
+    c.bench_function("weight: rsa verify", |b| {
+        b.iter(|| pubkey.verify(&digest, &signature).unwrap())
+    });
+    // CA_VERIFY_WEIGHT
+    c.bench_function("weight: check against a CA", |b| {
+        b.iter(|| create.check_ca(&[ca.clone()]).unwrap())
+    });
+    // CERT_BYTE_WEIGHT, divided by `cert.len()`
+    c.bench_function("weight: parse and hash a cert", |b| {
+        b.iter(|| {
+            let info = x509::parse_cert(&cert[..]).unwrap();
+            types::RsaPublicKey::from_der(info.public_key).unwrap();
+            sp_io::hashing::blake2_256(&cert[..]);
+            sp_io::hashing::blake2_256(&cert[..]);
+        })
+    });
+}
+
+criterion_group!(benches, steps);
+criterion_main!(benches);
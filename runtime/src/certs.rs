@@ -1,5 +1,7 @@
 use hex_literal::hex;
 
+/// JPKI user authentication CAs trusted at genesis.
+/// Later changes go through `add_ca` / `retire_ca`.
 pub const auth_ca: [&[u8]; 2] = [
     &hex!("3082052030820408a003020102020101300d06092a864886f70d01010b0500308182310b3009060355040613024a50310d300b060355040a0c044a504b4931253023060355040b0c1c4a504b4920666f7220757365722061757468656e7469636174696f6e313d303b060355040b0c344a6170616e204167656e637920666f72204c6f63616c20417574686f7269747920496e666f726d6174696f6e2053797374656d73301e170d3135313032303033303831335a170d3235313031393134353935395a308182310b3009060355040613024a50310d300b060355040a0c044a504b4931253023060355040b0c1c4a504b4920666f7220757365722061757468656e7469636174696f6e313d303b060355040b0c344a6170616e204167656e637920666f72204c6f63616c20417574686f7269747920496e666f726d6174696f6e2053797374656d7330820122300d06092a864886f70d01010105000382010f003082010a0282010100c4accad3c0e232d15ecb1d8081b6dcda02a85eb5fa2825ff05d183121952019d90f3c4acf8b68343598e2637ddf9528f6c68f4ec4edb0845e7796b31407a41375614ca4ab7e1037f6e1acb090541227eac5acb7598f1788020f1398c1e41f0673a29e77d1af9b3c00ed354dc60e4cf6ae48d3e831b49da872a316388ec0f2d7cbd94a703aac9560a4595578ca22404f21b62b34b759ac0922dc5b020b297bad1e3b34eb77b0b656d68cf67f125466e6c3ee8017cca9998687d4f73dca406e9d81368dccea673ca867c5b4f05ccebccd05b9d87f28922affea0276de002bf9f47c67e20cb8a9028fb75ffbb2eff9a6ffdd922771bd35f902f95c4807b3b44bac70203010001a382019d30820199300e0603551d0f0101ff0404030201063081b70603551d110481af3081aca481a93081a6310b3009060355040613024a5031273025060355040a0c1ee585ace79a84e5808be4babae8aa8de8a8bce382b5e383bce38393e382b931393037060355040b0c30e585ace79a84e5808be4babae8aa8de8a8bce382b5e383bce38393e382b9e588a9e794a8e88085e8a8bce6988ee794a831333031060355040b0c2ae59cb0e696b9e585ace585b1e59ba3e4bd93e68385e5a0b1e382b7e382b9e38386e383a0e6a99fe6a78b300f0603551d130101ff040530030101ff30819c0603551d1f04819430819130818ea0818ba08188a48185308182310b3009060355040613024a50310d300b060355040a0c044a504b4931253023060355040b0c1c4a504b4920666f7220757365722061757468656e7469636174696f6e313d303b060355040b0c344a6170616e204167656e637920666f72204c6f63616c20417574686f7269747920496e666f726d6174696f6e2053797374656d73301d0603551d0e041604149567951b5ca70d84a0fff1d85a87f1aab1340385300d06092a864886f70d01010b0500038201010022679464b7d0e575f752220af14a076b46152d42ce7e7a795e02393ce536bf868689daf7a6c2cba6c2750bdc46b6ce3052bf4bbd6e9883bd21347f798507872b79689bfcc258cd19cb43533bcecc5bf0903b361ce2dfbfc9fefcbf08eab470228cf2639f2553370816a7bf831d252eea50f50b0cf97ff0880fbaa12c95f3fcab33e73b6a818a45243f3c5155d675534bb1ba5580eb28e30ab2fae1e1be5bb8adc93e4ee173b02a44cc78945cf2260fadaba432eb668be250a822a29f98f7e9e3e18c6a711f0ac0e2d49498a5ab8f9a914c08b33407cbab51495f0d2e56e2bb47db70f7c1c521d4b548f243189f27389a92a87b97a8a86f1e09691dd25cf59ace"),
     &hex!("308205233082040ba00302010202040133c349300d06092a864886f70d01010b0500308182310b3009060355040613024a50310d300b060355040a0c044a504b4931253023060355040b0c1c4a504b4920666f7220757365722061757468656e7469636174696f6e313d303b060355040b0c344a6170616e204167656e637920666f72204c6f63616c20417574686f7269747920496e666f726d6174696f6e2053797374656d73301e170d3139303931343233343135395a170d3239303931343134353935395a308182310b3009060355040613024a50310d300b060355040a0c044a504b4931253023060355040b0c1c4a504b4920666f7220757365722061757468656e7469636174696f6e313d303b060355040b0c344a6170616e204167656e637920666f72204c6f63616c20417574686f7269747920496e666f726d6174696f6e2053797374656d7330820122300d06092a864886f70d01010105000382010f003082010a0282010100945f7c265acd7169012267b3d9813f10b5ac049167ce9cfdae104c32f8314e4865adaec69c591dc138f67b43b01c865edcf8340c5d7852d765c46de86d85e7aa646d5a42efc8913a2240fb8e607f1e7c00a523dc5542adb456fdb008d45ec38315198bcce2ec29b5e81d719f8c3cd54fd12c7052a2a3a4d64c5ece6355514bb3cb400759a08996d06dd82f79684e4da6104f9c93a57b246abc95d502c967b0f6066a308ae630d4a56df78b6f163f29d0a37aeb1665e4fc985ccf199ff804464f3c1876b30fd052f540e75e410a6ab5b538187f2d5b67eeacfb33cdfd782c9c02dd46f62ffdbe69e1dac8cc0fed5d5cf029ca4350eadda8587ec0d5edbfa1f8530203010001a382019d30820199300e0603551d0f0101ff0404030201063081b70603551d110481af3081aca481a93081a6310b3009060355040613024a5031273025060355040a0c1ee585ace79a84e5808be4babae8aa8de8a8bce382b5e383bce38393e382b931393037060355040b0c30e585ace79a84e5808be4babae8aa8de8a8bce382b5e383bce38393e382b9e588a9e794a8e88085e8a8bce6988ee794a831333031060355040b0c2ae59cb0e696b9e585ace585b1e59ba3e4bd93e68385e5a0b1e382b7e382b9e38386e383a0e6a99fe6a78b300f0603551d130101ff040530030101ff30819c0603551d1f04819430819130818ea0818ba08188a48185308182310b3009060355040613024a50310d300b060355040a0c044a504b4931253023060355040b0c1c4a504b4920666f7220757365722061757468656e7469636174696f6e313d303b060355040b0c344a6170616e204167656e637920666f72204c6f63616c20417574686f7269747920496e666f726d6174696f6e2053797374656d73301d0603551d0e041604148cd5586a891485e559379b7e29d410cfd28b3593300d06092a864886f70d01010b0500038201010045aa7254ebb2318e827aeee9970df4ae2e7722d788fec6cc1a6b4eed32f37f8402c84a0286777dfbcfe99252a154448826fb9508ccff6573a5c8171c844edae29f640d6ccb9f203f1e48911136fe7c17a5c926218e8f4600fcd5630ad20f1c59ddcc3cdb007f47626f5d16ebfb81a4d22a019f14bfe56c519003f6071a34a373bf3b0809f2ac37901c5bba7a3252ed81361c1b4ea756788ab698fbbc4698d0b6512ef95616468280a73e62941e82716afe0f40aa4a58a52bf7d7d42a32d8729393eaa4e1b226663d1f6d708ba799e926e501b4aaee82c01c44826c05149ae35747ce56f461b5c2fba5b57186ea28307fbd70b85a88a6319221daa762f154c353")
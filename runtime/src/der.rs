@@ -0,0 +1,64 @@
+//! Just enough DER to build certificates and CRLs, for the tests and benches.
+//!
+//! Only built for tests and with the `test-utils` feature.
+
+pub const SHA256_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b];
+pub const RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+pub const COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+
+pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let len_bytes: Vec<u8> = len
+            .to_be_bytes()
+            .iter()
+            .cloned()
+            .skip_while(|b| *b == 0)
+            .collect();
+        out.push(0x80 | len_bytes.len() as u8);
+        out.extend(len_bytes);
+    }
+    out.extend_from_slice(content);
+    out
+}
+pub fn seq(items: &[Vec<u8>]) -> Vec<u8> {
+    tlv(0x30, &items.concat())
+}
+pub fn set(items: &[Vec<u8>]) -> Vec<u8> {
+    tlv(0x31, &items.concat())
+}
+/// Unsigned big-endian integer
+pub fn uint(be: &[u8]) -> Vec<u8> {
+    let mut content: Vec<u8> = be.iter().cloned().skip_while(|b| *b == 0).collect();
+    if content.first().map_or(true, |b| b & 0x80 != 0) {
+        content.insert(0, 0);
+    }
+    tlv(0x02, &content)
+}
+pub fn oid(encoded: &[u8]) -> Vec<u8> {
+    tlv(0x06, encoded)
+}
+pub fn null() -> Vec<u8> {
+    vec![0x05, 0x00]
+}
+pub fn bit_string(bytes: &[u8]) -> Vec<u8> {
+    tlv(0x03, &[&[0u8][..], bytes].concat())
+}
+pub fn utc_time(time: &str) -> Vec<u8> {
+    tlv(0x17, time.as_bytes())
+}
+pub fn explicit(n: u8, content: &[u8]) -> Vec<u8> {
+    tlv(0xa0 | n, content)
+}
+pub fn name(common_name: &str) -> Vec<u8> {
+    seq(&[set(&[seq(&[
+        oid(COMMON_NAME),
+        tlv(0x0c, common_name.as_bytes()),
+    ])])])
+}
+pub fn algorithm(oid_bytes: &[u8]) -> Vec<u8> {
+    seq(&[oid(oid_bytes), null()])
+}
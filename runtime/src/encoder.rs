@@ -1,12 +1,210 @@
-mod types;
+//! `myna-tx`: builds mynachain txs offline, so they can be signed on an air-gapped machine.
+//!
+//! Without `--signature` it prints the payload and the digest to be signed by the card.
+//! With `--signature` it assembles the `SignedData` ready for `mynachain_submit`.
+
+use codec::Encode;
+use node_template_runtime::{types, VERSION};
+use sp_core::{
+    bytes::{from_hex, to_hex},
+    H256,
+};
+use std::{collections::HashMap, env, fs, process};
+
+const USAGE: &str = "usage: myna-tx <command> --genesis <hex> [options]
+
+commands:
+  create-account --cert <file>
+  send           --from <id> --to <id> --amount <n> --nonce <n>
+  mint           --from <issuer id> --to <id> --amount <n> --nonce <n>
+  vote           --from <id> --amount <n> --nonce <n>
+  write          --from <id> --data <hex> --nonce <n>
+  withdraw       --from <id> --to <hex> --amount <n> --nonce <n>
+                 --to is the SCALE-encoded account of the Balances pallet
+  link-key       --from <id> --key <hex> --expires-at <term> --nonce <n> [--send-limit <n>]
+                 --key is the SCALE-encoded runtime account; without --send-limit
+                 the key may only write
+  unlink-key     --from <id> --key <hex> --nonce <n>
+  migrate-account --from <id> --new-cert <file> --nonce <n> [--new-signature <file>]
+                 the renewed card signs the digest printed without --new-signature,
+                 the old card the one printed with it
+
+options:
+  --genesis <hex>       genesis hash of the target chain
+  --spec-version <n>    runtime spec_version (default: this build's)
+  --signature <file>    raw card signature over the digest
+  --out <file>          write the SCALE-encoded SignedData to a file instead of stdout";
+
+struct Args {
+    command: String,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let command = args.next().ok_or("missing command")?;
+        let mut options = HashMap::new();
+        while let Some(key) = args.next() {
+            if !key.starts_with("--") {
+                return Err(format!("unexpected argument {}", key));
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for {}", key))?;
+            options.insert(key[2..].to_string(), value);
+        }
+        Ok(Args { command, options })
+    }
+
+    fn get(&self, key: &str) -> Result<&str, String> {
+        self.options
+            .get(key)
+            .map(|v| v.as_str())
+            .ok_or_else(|| format!("missing --{}", key))
+    }
+
+    fn number<N: std::str::FromStr>(&self, key: &str) -> Result<N, String> {
+        self.get(key)?
+            .parse()
+            .map_err(|_| format!("--{} is not a number", key))
+    }
+
+    fn hex(&self, key: &str) -> Result<Vec<u8>, String> {
+        from_hex(self.get(key)?).map_err(|e| format!("--{}: {:?}", key, e))
+    }
+
+    fn hash(&self, key: &str) -> Result<H256, String> {
+        let bytes = self.hex(key)?;
+        if bytes.len() != 32 {
+            return Err(format!("--{} must be 32 bytes", key));
+        }
+        Ok(H256::from_slice(&bytes))
+    }
+
+    fn file(&self, key: &str) -> Result<Vec<u8>, String> {
+        let path = self.get(key)?;
+        fs::read(path).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+/// The tx and the account it is sent from
+fn build_tx(args: &Args, ctx: &types::SigningContext) -> Result<(types::Tx, types::AccountId), String> {
+    Ok(match args.command.as_str() {
+        "create-account" => {
+            let cert = args.file("cert")?;
+            let id = ctx.fingerprint(&cert[..]);
+            (
+                types::Tx::CreateAccount(types::TxCreateAccount { cert, nonce: 0 }),
+                id,
+            )
+        }
+        "send" => (
+            types::Tx::Send(types::TxSend {
+                to: args.hash("to")?,
+                amount: args.number("amount")?,
+                nonce: args.number("nonce")?,
+            }),
+            args.hash("from")?,
+        ),
+        "mint" => (
+            types::Tx::Mint(types::TxMint {
+                to: args.hash("to")?,
+                amount: args.number("amount")?,
+                nonce: args.number("nonce")?,
+            }),
+            args.hash("from")?,
+        ),
+        "vote" => (
+            types::Tx::Vote(types::TxVote {
+                amount: args.number("amount")?,
+                nonce: args.number("nonce")?,
+            }),
+            args.hash("from")?,
+        ),
+        "write" => (
+            types::Tx::Write(types::TxWrite {
+                data: args.hex("data")?,
+                nonce: args.number("nonce")?,
+            }),
+            args.hash("from")?,
+        ),
+        "withdraw" => (
+            types::Tx::Withdraw(types::TxWithdraw {
+                to: args.hex("to")?,
+                amount: args.number("amount")?,
+                nonce: args.number("nonce")?,
+            }),
+            args.hash("from")?,
+        ),
+        "link-key" => (
+            types::Tx::LinkKey(types::TxLinkKey {
+                key: args.hex("key")?,
+                scope: match args.options.get("send-limit") {
+                    Some(_) => types::KeyScope::Spend {
+                        limit: args.number("send-limit")?,
+                    },
+                    None => types::KeyScope::WriteOnly,
+                },
+                expires_at: args.number("expires-at")?,
+                nonce: args.number("nonce")?,
+            }),
+            args.hash("from")?,
+        ),
+        "unlink-key" => (
+            types::Tx::UnlinkKey(types::TxUnlinkKey {
+                key: args.hex("key")?,
+                nonce: args.number("nonce")?,
+            }),
+            args.hash("from")?,
+        ),
+        "migrate-account" => (
+            types::Tx::MigrateAccount(types::TxMigrateAccount {
+                new_cert: args.file("new-cert")?,
+                new_signature: match args.options.get("new-signature") {
+                    Some(_) => args.file("new-signature")?,
+                    None => vec![],
+                },
+                nonce: args.number("nonce")?,
+            }),
+            args.hash("from")?,
+        ),
+        command => return Err(format!("unknown command {}", command)),
+    })
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let ctx = types::SigningContext {
+        genesis_hash: args.hash("genesis")?,
+        spec_version: match args.options.get("spec-version") {
+            Some(_) => args.number("spec-version")?,
+            None => VERSION.spec_version,
+        },
+    };
+    let (tbs, id) = build_tx(&args, &ctx)?;
+
+    if !args.options.contains_key("signature") {
+        println!("payload: {}", to_hex(&ctx.payload(&tbs), false));
+        println!("digest:  {}", to_hex(ctx.sighash(&tbs).as_bytes(), false));
+        return Ok(());
+    }
+
+    let signed = types::SignedData {
+        tbs,
+        signature: args.file("signature")?,
+        id,
+    };
+    let encoded = signed.encode();
+    match args.options.get("out") {
+        Some(path) => fs::write(path, &encoded).map_err(|e| format!("{}: {}", path, e))?,
+        None => println!("{}", to_hex(&encoded, false)),
+    }
+    Ok(())
+}
 
 fn main() {
-    println!("encoder");
-    let tx = types::TxCreateAccount {
-        cert: vec![1,2,3,4,5,6,7,8],
-        nonce: 0
-    }
-    let tbs = types::Tx::CreateAccount(tx);
-    let encoded = tbs.encode();
-    println!("{:?}", encoded);
+    let result = Args::parse(env::args().skip(1)).and_then(run);
+    if let Err(e) = result {
+        eprintln!("error: {}\n\n{}", e, USAGE);
+        process::exit(1);
+    }
 }
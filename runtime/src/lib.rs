@@ -62,9 +62,15 @@ pub type Hash = sp_core::H256;
 pub type DigestItem = generic::DigestItem<Hash>;
 
 /// Used for the module template in `./mynachain.rs`
-mod mynachain;
+pub mod mynachain;
 pub mod certs;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod der;
 pub mod types;
+pub mod weights;
+pub mod x509;
+#[cfg(test)]
+mod mock;
 /// Opaque types. These are used by the CLI to instantiate machinery that don't need to know
 /// the specifics of the runtime. They can then be made to be agnostic over specific formats
 /// of data like extrinsics, allowing for them to continue syncing the network through upgrades
@@ -89,6 +95,36 @@ pub mod opaque {
     }
 }
 
+sp_api::decl_runtime_apis! {
+    /// Read access to mynachain state for wallets, so they don't need to build storage keys
+    pub trait MynachainApi {
+        /// Balance including the distribution accrued so far
+        fn balance(id: types::AccountId) -> Option<types::Balance>;
+        fn nonce(id: types::AccountId) -> Option<types::Nonce>;
+        fn account(id: types::AccountId) -> Option<types::Account>;
+        /// `AccountExpired` once the cert is past notAfter, `Revoked` once it is on a CRL
+        fn account_status(id: types::AccountId) -> Option<types::AccountStatus>;
+        fn term_number() -> types::TermNumber;
+        /// Cumulative votes as of `term`
+        fn votes(term: types::TermNumber) -> types::Balance;
+        fn vote_of(term: types::TermNumber, id: types::AccountId) -> Option<types::Balance>;
+        /// Sum of all balances, accrued distribution included
+        fn total_issuance() -> types::Balance;
+    }
+
+    /// Not for wallets: `tests/rsa_host_function.rs` calls it to run
+    /// `myna_crypto::fallback::rsa_verify_pkcs1_sha256` inside the Wasm runtime
+    /// and cross-check it against the host function
+    pub trait MynaCryptoTestApi {
+        fn rsa_verify_pkcs1_sha256_fallback(
+            modulus: Vec<u8>,
+            exponent: Vec<u8>,
+            digest: Vec<u8>,
+            signature: Vec<u8>,
+        ) -> bool;
+    }
+}
+
 /// This runtime version.
 pub const VERSION: RuntimeVersion = RuntimeVersion {
     spec_name: create_runtime_str!("node-template"),
@@ -234,9 +270,18 @@ impl sudo::Trait for Runtime {
     type Proposal = Call;
 }
 
+parameter_types! {
+    pub const TermDuration: BlockNumber = DAYS;
+    pub const CrlFetchInterval: BlockNumber = HOURS;
+}
+
 /// Used for the module template in `./template.rs`
 impl mynachain::Trait for Runtime {
     type Event = Event;
+    type TermDuration = TermDuration;
+    type Call = Call;
+    type SubmitTransaction = system::offchain::TransactionSubmitter<(), Runtime, UncheckedExtrinsic>;
+    type CrlFetchInterval = CrlFetchInterval;
 }
 
 construct_runtime!(
@@ -254,7 +299,7 @@ construct_runtime!(
 		TransactionPayment: transaction_payment::{Module, Storage},
 		Sudo: sudo,
 		// Used for the module template in `./mynachain.rs`
-		MynaChainModule: mynachain::{Module, Call, Storage, Event},
+		MynaChainModule: mynachain::{Module, Call, Storage, Event<T>, Config, ValidateUnsigned},
 		RandomnessCollectiveFlip: randomness_collective_flip::{Module, Call, Storage},
 	}
 );
@@ -366,4 +411,49 @@ impl_runtime_apis! {
             Grandpa::grandpa_authorities()
         }
     }
+
+    impl self::MynachainApi<Block> for Runtime {
+        fn balance(id: types::AccountId) -> Option<types::Balance> {
+            MynaChainModule::compute_balance(id).ok()
+        }
+
+        fn nonce(id: types::AccountId) -> Option<types::Nonce> {
+            MynaChainModule::account_of(id).map(|account| account.nonce)
+        }
+
+        fn account(id: types::AccountId) -> Option<types::Account> {
+            MynaChainModule::account_of(id)
+        }
+
+        fn account_status(id: types::AccountId) -> Option<types::AccountStatus> {
+            MynaChainModule::account_status(id)
+        }
+
+        fn term_number() -> types::TermNumber {
+            MynaChainModule::term_number()
+        }
+
+        fn votes(term: types::TermNumber) -> types::Balance {
+            MynaChainModule::votes_cum(term)
+        }
+
+        fn vote_of(term: types::TermNumber, id: types::AccountId) -> Option<types::Balance> {
+            MynaChainModule::vote_of(term, id)
+        }
+
+        fn total_issuance() -> types::Balance {
+            MynaChainModule::total_issuance()
+        }
+    }
+
+    impl self::MynaCryptoTestApi<Block> for Runtime {
+        fn rsa_verify_pkcs1_sha256_fallback(
+            modulus: Vec<u8>,
+            exponent: Vec<u8>,
+            digest: Vec<u8>,
+            signature: Vec<u8>,
+        ) -> bool {
+            myna_crypto::fallback::rsa_verify_pkcs1_sha256(&modulus, &exponent, &digest, &signature)
+        }
+    }
 }
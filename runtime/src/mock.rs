@@ -0,0 +1,345 @@
+//! Mock runtime for the mynachain tests, with a test CA and software My Number cards.
+//!
+//! The cards behave like the JPKI user authentication key: PKCS#1 v1.5 signatures
+//! over a SHA-256 `DigestInfo` wrapping the 32 byte digest handed to them.
+
+use crate::{der, mynachain, types};
+use frame_support::{impl_outer_dispatch, impl_outer_origin, parameter_types, weights::Weight};
+use lazy_static::lazy_static;
+use rand::{rngs::StdRng, SeedableRng};
+use rsa::{hash::Hashes, PaddingScheme, PublicKey, RSAPrivateKey};
+use sp_core::{
+    offchain::{
+        testing::{OffchainState, PoolState, TestOffchainExt, TestTransactionPoolExt},
+        OffchainExt, TransactionPoolExt,
+    },
+    H256,
+};
+use sp_runtime::{
+    testing::{Header, TestXt},
+    traits::{BlakeTwo256, IdentityLookup},
+    Perbill,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use parking_lot::RwLock;
+
+impl_outer_origin! {
+    pub enum Origin for Test {}
+}
+
+impl_outer_dispatch! {
+    pub enum Call for Test where origin: Origin {
+        mynachain::MynaChainModule,
+    }
+}
+
+/// What the offchain worker submits to the pool
+pub type Extrinsic = TestXt<Call, ()>;
+
+// For testing the module, we construct most of a mock runtime. This means
+// first constructing a configuration type (`Test`) which `impl`s each of the
+// configuration traits of modules we want to use.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Test;
+parameter_types! {
+    pub const BlockHashCount: u64 = 250;
+    pub const MaximumBlockWeight: Weight = 1024;
+    pub const MaximumBlockLength: u32 = 2 * 1024;
+    pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
+}
+impl system::Trait for Test {
+    type Origin = Origin;
+    type Call = ();
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = ();
+    type BlockHashCount = BlockHashCount;
+    type MaximumBlockWeight = MaximumBlockWeight;
+    type MaximumBlockLength = MaximumBlockLength;
+    type AvailableBlockRatio = AvailableBlockRatio;
+    type Version = ();
+    type ModuleToIndex = ();
+}
+parameter_types! {
+    pub const ExistentialDeposit: u64 = 0;
+    pub const TransferFee: u64 = 0;
+    pub const CreationFee: u64 = 0;
+}
+impl balances::Trait for Test {
+    type Balance = u64;
+    type OnFreeBalanceZero = ();
+    type OnNewAccount = ();
+    type Event = ();
+    type DustRemoval = ();
+    type TransferPayment = ();
+    type ExistentialDeposit = ExistentialDeposit;
+    type TransferFee = TransferFee;
+    type CreationFee = CreationFee;
+}
+parameter_types! {
+    pub const MinimumPeriod: u64 = 1;
+}
+impl timestamp::Trait for Test {
+    type Moment = u64;
+    type OnTimestampSet = ();
+    type MinimumPeriod = MinimumPeriod;
+}
+parameter_types! {
+    pub const TermDuration: u64 = 10;
+    pub const CrlFetchInterval: u64 = 5;
+}
+impl mynachain::Trait for Test {
+    type Event = ();
+    type TermDuration = TermDuration;
+    type Call = Call;
+    type SubmitTransaction = system::offchain::TransactionSubmitter<(), Call, Extrinsic>;
+    type CrlFetchInterval = CrlFetchInterval;
+}
+pub type System = system::Module<Test>;
+pub type Balances = balances::Module<Test>;
+pub type Timestamp = timestamp::Module<Test>;
+pub type MynaChainModule = mynachain::Module<Test>;
+
+/// 2021-01-01T00:00:00Z, in milliseconds
+pub const GENESIS_TIME: u64 = 1_609_459_200_000;
+
+// This function basically just builds a genesis storage key/value store according to
+// our desired mockup. The test CA is the only trusted one.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut t = system::GenesisConfig::default()
+        .build_storage::<Test>()
+        .unwrap();
+    mynachain::GenesisConfig {
+        trusted_cas: vec![TEST_CA.cert.clone()],
+    }
+    .assimilate_storage::<Test>(&mut t)
+    .unwrap();
+    let mut ext: sp_io::TestExternalities = t.into();
+    ext.execute_with(|| Timestamp::set_timestamp(GENESIS_TIME));
+    ext
+}
+
+/// `new_test_ext` with a fake HTTP/offchain storage and a fake transaction pool
+pub fn new_offchain_test_ext() -> (
+    sp_io::TestExternalities,
+    Arc<RwLock<OffchainState>>,
+    Arc<RwLock<PoolState>>,
+) {
+    let mut ext = new_test_ext();
+    let (offchain, offchain_state) = TestOffchainExt::new();
+    let (pool, pool_state) = TestTransactionPoolExt::new();
+    ext.register_extension(OffchainExt::new(offchain));
+    ext.register_extension(TransactionPoolExt::new(pool));
+    (ext, offchain_state, pool_state)
+}
+
+/// `notBefore`, `notAfter` as UTCTime
+pub type Validity = (&'static str, &'static str);
+pub const DEFAULT_VALIDITY: Validity = ("200101000000Z", "491231235959Z");
+
+fn spki(key: &RSAPrivateKey) -> Vec<u8> {
+    let rsa_public_key = der::seq(&[
+        der::uint(&key.n().to_bytes_be()),
+        der::uint(&key.e().to_bytes_be()),
+    ]);
+    der::seq(&[
+        der::algorithm(der::RSA_ENCRYPTION),
+        der::bit_string(&rsa_public_key),
+    ])
+}
+
+/// PKCS#1 v1.5 with a SHA-256 `DigestInfo`, as the card does
+fn sign_digest(key: &RSAPrivateKey, digest: &[u8]) -> Vec<u8> {
+    key.sign(PaddingScheme::PKCS1v15, Some(&Hashes::SHA2_256), digest)
+        .expect("digest is 32 bytes; qed")
+}
+
+/// A certificate authority living in memory
+pub struct TestCa {
+    key: RSAPrivateKey,
+    name: Vec<u8>,
+    pub cert: Vec<u8>,
+}
+
+impl TestCa {
+    pub fn new(key: RSAPrivateKey, common_name: &str) -> Self {
+        Self::with_validity(key, common_name, DEFAULT_VALIDITY)
+    }
+
+    pub fn with_validity(key: RSAPrivateKey, common_name: &str, validity: Validity) -> Self {
+        let name = der::name(common_name);
+        let mut ca = TestCa {
+            key,
+            name,
+            cert: vec![],
+        };
+        ca.cert = ca.sign_cert(0, &ca.name, &spki(&ca.key), validity);
+        ca
+    }
+
+    fn sign_cert(&self, serial: u64, subject: &[u8], spki: &[u8], validity: Validity) -> Vec<u8> {
+        let tbs = der::seq(&[
+            der::explicit(0, &der::uint(&[2])),
+            der::uint(&serial.to_be_bytes()),
+            der::algorithm(der::SHA256_WITH_RSA),
+            self.name.clone(),
+            der::seq(&[der::utc_time(validity.0), der::utc_time(validity.1)]),
+            subject.to_vec(),
+            spki.to_vec(),
+        ]);
+        let signature = sign_digest(&self.key, &sp_io::hashing::sha2_256(&tbs));
+        der::seq(&[
+            tbs,
+            der::algorithm(der::SHA256_WITH_RSA),
+            der::bit_string(&signature),
+        ])
+    }
+
+    /// Issue a user authentication cert for `key`
+    pub fn issue(&self, key: &RSAPrivateKey, serial: u64, validity: Validity) -> Vec<u8> {
+        self.sign_cert(serial, &der::name(""), &spki(key), validity)
+    }
+
+    /// A v2 CRL revoking `serials` as of `this_update` (UTCTime)
+    pub fn crl(&self, serials: &[u64], this_update: &str) -> Vec<u8> {
+        let mut tbs = vec![
+            der::uint(&[1]),
+            der::algorithm(der::SHA256_WITH_RSA),
+            self.name.clone(),
+            der::utc_time(this_update),
+        ];
+        if !serials.is_empty() {
+            let entries: Vec<Vec<u8>> = serials
+                .iter()
+                .map(|serial| der::seq(&[der::uint(&serial.to_be_bytes()), der::utc_time(this_update)]))
+                .collect();
+            tbs.push(der::seq(&entries));
+        }
+        let tbs = der::seq(&tbs);
+        let signature = sign_digest(&self.key, &sp_io::hashing::sha2_256(&tbs));
+        der::seq(&[
+            tbs,
+            der::algorithm(der::SHA256_WITH_RSA),
+            der::bit_string(&signature),
+        ])
+    }
+}
+
+/// A My Number card: the auth key and its cert
+pub struct Card {
+    key: RSAPrivateKey,
+    pub cert: Vec<u8>,
+}
+
+impl Card {
+    pub fn new(key: RSAPrivateKey, cert: Vec<u8>) -> Self {
+        Card { key, cert }
+    }
+
+    /// The account this card registers
+    pub fn id(&self) -> types::AccountId {
+        MynaChainModule::fingerprint(&self.cert[..])
+    }
+
+    /// What the account of this card should hold
+    pub fn public_key(&self) -> types::RsaPublicKey {
+        types::RsaPublicKey {
+            modulus: self.key.n().to_bytes_be(),
+            exponent: self.key.e().to_bytes_be(),
+        }
+    }
+
+    pub fn sign_digest(&self, digest: &[u8]) -> Vec<u8> {
+        sign_digest(&self.key, digest)
+    }
+
+    /// Sign `tbs` for the chain in the current externalities
+    pub fn sign(&self, tbs: types::Tx) -> types::SignedData {
+        self.sign_for(self.id(), tbs)
+    }
+
+    /// Sign `tbs` for account `id`, which this card holds after a migration
+    pub fn sign_for(&self, id: types::AccountId, tbs: types::Tx) -> types::SignedData {
+        let digest = MynaChainModule::signing_context().sighash(&tbs);
+        types::SignedData {
+            signature: self.sign_digest(digest.as_bytes()),
+            tbs,
+            id,
+        }
+    }
+
+    /// Move account `id`, held by this card, to `renewed`
+    pub fn migrate(&self, id: types::AccountId, renewed: &Card, nonce: types::Nonce) -> types::SignedData {
+        let mut tbs = types::TxMigrateAccount {
+            new_cert: renewed.cert.clone(),
+            new_signature: vec![],
+            nonce,
+        };
+        let digest = MynaChainModule::signing_context().sighash(&tbs.unsigned());
+        tbs.new_signature = renewed.sign_digest(digest.as_bytes());
+        self.sign_for(id, types::Tx::MigrateAccount(tbs))
+    }
+
+    pub fn create_account(&self) -> types::SignedData {
+        self.sign(types::Tx::CreateAccount(types::TxCreateAccount {
+            cert: self.cert.clone(),
+            nonce: 0,
+        }))
+    }
+}
+
+/// Keys are small and cached: generating them is the slow part of the tests
+const KEY_BITS: usize = 1024;
+
+lazy_static! {
+    static ref KEYS: Mutex<HashMap<u64, RSAPrivateKey>> = Mutex::new(HashMap::new());
+    pub static ref TEST_CA: TestCa = TestCa::new(key(0), "Mynachain Test CA");
+    /// Looks like a CA but isn't trusted by the mock runtime
+    pub static ref ROGUE_CA: TestCa = TestCa::new(key(1), "Rogue CA");
+    /// Its own cert ran out at the end of 2015
+    pub static ref EXPIRED_CA: TestCa =
+        TestCa::with_validity(key(2), "Expired CA", ("100101000000Z", "151231235959Z"));
+}
+
+/// Deterministic key number `seed`
+pub fn key(seed: u64) -> RSAPrivateKey {
+    KEYS.lock()
+        .unwrap()
+        .entry(seed)
+        .or_insert_with(|| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            RSAPrivateKey::new(&mut rng, KEY_BITS).expect("key generation works; qed")
+        })
+        .clone()
+}
+
+/// The `n`th card issued by the test CA. Same `n`, same card.
+pub fn card(n: u64) -> Card {
+    card_with_validity(n, DEFAULT_VALIDITY)
+}
+
+pub fn card_with_validity(n: u64, validity: Validity) -> Card {
+    let key = key(n + 100);
+    let cert = TEST_CA.issue(&key, n + 1, validity);
+    Card::new(key, cert)
+}
+
+/// The `n`th card issued by `ca`
+pub fn card_of(ca: &TestCa, n: u64) -> Card {
+    let key = key(n + 100);
+    let cert = ca.issue(&key, n + 1, DEFAULT_VALIDITY);
+    Card::new(key, cert)
+}
+
+/// A card whose cert chains to `ROGUE_CA`
+pub fn rogue_card(n: u64) -> Card {
+    card_of(&ROGUE_CA, n)
+}
@@ -1,25 +1,63 @@
-use crate::{types};
+use crate::{certs, types, weights, x509};
 use frame_support::{
-    decl_event, decl_module, decl_storage,
+    debug, decl_error, decl_event, decl_module, decl_storage,
     dispatch::{Decode, DispatchError, DispatchResult, Encode, Vec},
     ensure,
-    traits::{Currency, ExistenceRequirement},
+    storage::unhashed,
+    traits::{Currency, EnsureOrigin, ExistenceRequirement, Get, WithdrawReason},
     weights::Weight,
 };
-use myna::crypto;
-use sp_std::vec;
-use system::{ensure_none, ensure_root, ensure_signed};
+use sp_std::{marker::PhantomData, vec};
+use system::{ensure_none, ensure_root, ensure_signed, offchain::SubmitUnsignedTransaction};
 
 use core::convert::TryInto;
-use sp_core::{Blake2Hasher, Hasher};
-use sp_runtime::traits::CheckedDiv;
+use sp_core::{Blake2Hasher, Hasher, H256};
+use sp_runtime::offchain::{http, Duration, StorageKind};
+use sp_runtime::traits::{CheckedDiv, SaturatedConversion, Zero};
+use sp_runtime::transaction_validity::{
+    InvalidTransaction, TransactionLongevity, TransactionValidity, UnknownTransaction,
+    ValidTransaction,
+};
 
 pub const MAX_VOTE_BALANCE_PER_TERM: types::Balance = 10000;
+/// How much a single person can vote for in one term
+pub const MAX_VOTE_BALANCE_PER_ACCOUNT: types::Balance = 100;
+/// Longest `TxWrite::data`. Writes come in unsigned and pay no fee, so this bounds
+/// how much state a card can add per tx.
+pub const MAX_DATA_LEN: usize = 1024;
+/// `InvalidTransaction::Custom` code for txs from an expired account
+pub const ACCOUNT_EXPIRED: u8 = 1;
+/// `InvalidTransaction::Custom` code for txs from a revoked account
+pub const ACCOUNT_REVOKED: u8 = 2;
+/// Offchain storage key (`PERSISTENT`) of the URL the offchain worker fetches the CRL from
+pub const CRL_URL_KEY: &[u8] = b"mynachain::crl-url";
+/// How long the offchain worker waits for the CRL
+const CRL_FETCH_TIMEOUT_MS: u64 = 10_000;
+/// 0: no `StorageVersion` yet, possibly no `TrustedCas` either
+/// 1: `Accounts` hold a DER public key and a fingerprint instead of the cert
+/// 2: `Accounts` hold the decoded modulus and exponent
+/// 3: balances are `Ledgers` instead of `RawBalance`
+/// 4: `TotalIssuance` and `EligibleAccounts` are tracked
+pub const STORAGE_VERSION: u32 = 4;
+/// Accounts `migrate_storage` brings up to `STORAGE_VERSION` per block. Each costs a CA
+/// check, against the CA named as the cert's issuer.
+pub const MIGRATION_BATCH: u64 = 50;
+/// Amounts on the Balances side of the bridge
+pub type BalanceOf<T> = <T as balances::Trait>::Balance;
+
 /// The module's configuration trait.
-pub trait Trait: balances::Trait {
+pub trait Trait: balances::Trait + timestamp::Trait {
     // TODO: Add other types and constants required configure this module.
     /// The overarching event type.
-    type Event: From<Event> + Into<<Self as system::Trait>::Event>;
+    type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+    /// Number of blocks per term
+    type TermDuration: Get<Self::BlockNumber>;
+    /// The overarching call type, for txs from the offchain worker
+    type Call: From<Call<Self>>;
+    /// Submits the CRLs the offchain worker fetched
+    type SubmitTransaction: SubmitUnsignedTransaction<Self, <Self as Trait>::Call>;
+    /// Number of blocks between CRL fetches of the offchain worker
+    type CrlFetchInterval: Get<Self::BlockNumber>;
 }
 
 // This module's storage items.
@@ -28,39 +66,419 @@ decl_storage! {
         AccountCount get(fn account_count): u64;
         AccountEnumerator get(fn account_enum): map u64 => types::AccountId;
         Accounts get(fn account): map types::AccountId => types::Account;
-        RawBalance get(fn balance): map types::AccountId => types::Balance;
+        /// Settled funds of each account
+        Ledgers get(fn ledger): map types::AccountId => types::Ledger;
+        /// Balances up to storage version 2, moved into `Ledgers` by `migrate_storage`
+        RawBalance: map types::AccountId => types::Balance;
+        /// Sum of all balances: everything minted, distributed and deposited, less what was withdrawn
+        TotalIssuance get(fn total_issuance): types::Balance;
+        /// Number of accounts created before the current term, which each vote pays
+        EligibleAccounts get(fn eligible_accounts): u64;
         TermNumber get(fn term_number): types::TermNumber;
         CumulativeVotes get(fn votes_cum): map types::TermNumber => types::Balance; // 投票の累積和。ちなみにゲッターのcumはCumulativeのprefixです。念の為。
+        Issuers get(fn issuer): map types::AccountId => Option<types::Issuer>;
+        MintedInTerm get(fn minted_in_term): double_map types::TermNumber, blake2_256(types::AccountId) => types::Balance;
+        VotesByAccount get(fn vote_of): double_map types::TermNumber, blake2_256(types::AccountId) => Option<types::Balance>;
+        /// CAs whose user certs may create accounts
+        TrustedCas get(fn trusted_ca): linked_map types::CaId => types::CaRecord<T::BlockNumber>;
+        /// Serials on each CA's revocation list
+        Revoked get(fn is_revoked): double_map types::CaId, blake2_256(Vec<u8>) => bool;
+        /// Account allowed to submit CRLs besides root
+        CrlOracle get(fn crl_oracle): Option<T::AccountId>;
+        /// Renewed certs (by fingerprint, or by `Blake2(cert)` before ids were salted)
+        /// and the account they were migrated into
+        MigratedCerts get(fn migrated_cert): map H256 => Option<types::AccountId>;
+        /// Keys of the runtime linked to an account, offered by `TxLinkKey` and accepted
+        /// by the key with `accept_key`
+        LinkedKeys get(fn linked_key): map T::AccountId => Option<types::LinkedKey>;
+        /// Links offered by `TxLinkKey` the key hasn't accepted yet
+        KeyOffers get(fn key_offer): double_map T::AccountId, blake2_256(types::AccountId) => Option<types::LinkedKey>;
+        /// Layout version of this module's storage, see `STORAGE_VERSION`
+        StorageVersion get(fn storage_version): u32;
+        /// Accounts, by `AccountEnumerator` index, already migrated while `StorageVersion`
+        /// is behind
+        MigrationCursor get(fn migration_cursor): u64;
+    }
+    add_extra_genesis {
+        config(trusted_cas): Vec<Vec<u8>>;
+        build(|config: &GenesisConfig| {
+            for cert in config.trusted_cas.iter() {
+                Module::<T>::insert_ca(cert.clone(), Zero::zero());
+            }
+            StorageVersion::put(STORAGE_VERSION);
+        });
     }
 }
 
 decl_event!(
-    pub enum Event {
+    pub enum Event<T>
+    where
+        AccountId = <T as system::Trait>::AccountId,
+        BlockNumber = <T as system::Trait>::BlockNumber,
+    {
         AccountAdd(types::AccountId),
+        /// account, fingerprint of its new cert
+        AccountMigrated(types::AccountId, H256),
         Transferred(types::AccountId, types::AccountId, types::Balance),
-        Minted(types::AccountId, types::Balance),
+        /// issuer, recipient, amount
+        Minted(types::AccountId, types::AccountId, types::Balance),
+        IssuerAdded(types::AccountId, types::Balance),
+        IssuerRemoved(types::AccountId),
         Voted(types::AccountId, types::Balance),
         Written(types::AccountId),
-        NextTerm(types::TermNumber),
+        /// new term, block it started at
+        NextTerm(types::TermNumber, BlockNumber),
+        /// CA, block it is trusted from
+        CaAdded(types::CaId, BlockNumber),
+        /// CA, block it stops being trusted at
+        CaRetired(types::CaId, BlockNumber),
+        CaRemoved(types::CaId),
+        /// CA, number of serials on the CRL
+        CrlUpdated(types::CaId, u32),
+        CrlOracleSet(Option<AccountId>),
+        /// from the Balances pallet: sender, recipient, amount
+        Deposited(AccountId, types::AccountId, types::Balance),
+        /// to the Balances pallet: sender, recipient, amount
+        Withdrawn(types::AccountId, AccountId, types::Balance),
+        /// account, key, last term of the key
+        KeyOffered(types::AccountId, AccountId, types::TermNumber),
+        /// account, key, last term of the key
+        KeyLinked(types::AccountId, AccountId, types::TermNumber),
+        KeyUnlinked(types::AccountId, AccountId),
         AlwaysOk,
     }
 );
+
+decl_error! {
+    /// Why a mynachain call failed
+    pub enum Error for Module<T: Trait> {
+        AccountNotFound,
+        AccountAlreadyExists,
+        /// The card expired; the holder has to migrate to a renewed one
+        AccountExpired,
+        /// The card's cert was revoked
+        AccountRevoked,
+        /// The cert already backs an account, or was migrated into one
+        CertificateInUse,
+        /// The DER cert is malformed
+        InvalidCertificate,
+        CertificateNotYetValid,
+        CertificateExpired,
+        CertificateRevoked,
+        /// No active trusted CA issued the cert
+        UnknownCa,
+        /// The cert's public key is malformed
+        InvalidPublicKey,
+        /// The card signature doesn't match
+        BadSignature,
+        /// Accounts are created with nonce zero
+        NonceNotZero,
+        /// This kind of tx has no nonce
+        NoNonce,
+        /// The nonce was already used
+        StaleNonce,
+        /// Txs with lower nonces have to come first
+        FutureNonce,
+        /// Amounts must be positive
+        InvalidAmount,
+        InsufficientBalance,
+        Overflow,
+        /// More than `MAX_VOTE_BALANCE_PER_ACCOUNT`
+        VoteTooLarge,
+        /// The term's votes would exceed `MAX_VOTE_BALANCE_PER_TERM`
+        TermVotesExceeded,
+        AlreadyVoted,
+        NotAnIssuer,
+        NegativeMintCap,
+        MintCapExceeded,
+        CaAlreadyExists,
+        CaNotFound,
+        /// The CA is retired already, at the same or an earlier block
+        CaAlreadyRetired,
+        /// Only retired CAs can be removed
+        CaNotRetired,
+        /// `weights::MAX_ACTIVE_CAS` CAs are trusted or scheduled already
+        TooManyCas,
+        /// Accounts are still being migrated to `STORAGE_VERSION`
+        MigrationPending,
+        /// Terms advance automatically, not by tx
+        NextTermByTx,
+        /// Only root and the CRL oracle may update CRLs
+        NotCrlOracle,
+        /// The DER CRL is malformed
+        InvalidCrl,
+        /// Longer than `weights::MAX_CRL_LEN`
+        CrlTooLarge,
+        /// No trusted CA signed the CRL
+        UntrustedCrl,
+        /// The offchain worker has no `CRL_URL_KEY`
+        CrlUrlNotSet,
+        CrlUrlNotUtf8,
+        CrlRequestFailed,
+        CrlRequestTimedOut,
+        CrlSubmitFailed,
+        /// Longer than `MAX_DATA_LEN`
+        DataTooLarge,
+        /// `TxWithdraw::to` isn't an account of this runtime
+        InvalidDestination,
+        /// A new account of the Balances pallet needs at least the existential deposit
+        BelowExistentialDeposit,
+        /// `TxLinkKey::key` isn't an account of this runtime
+        InvalidKey,
+        /// The key is linked to another account
+        KeyInUse,
+        /// The caller isn't a key linked to an account
+        KeyNotLinked,
+        /// The account hasn't offered a link to the caller
+        KeyNotOffered,
+        /// The key is past its last term
+        KeyExpired,
+        /// The key's scope doesn't allow this call
+        KeyNotPermitted,
+        /// The key would send more than its limit
+        SendLimitExceeded,
+    }
+}
+
+impl<T: Trait> From<types::VerifyError> for Error<T> {
+    fn from(e: types::VerifyError) -> Self {
+        match e {
+            types::VerifyError::InvalidPublicKey => Error::InvalidPublicKey,
+            types::VerifyError::BadSignature => Error::BadSignature,
+            types::VerifyError::UnknownCa => Error::UnknownCa,
+        }
+    }
+}
 decl_module! {
     /// The module declaration.
     pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        type Error = Error<T>;
+
         fn deposit_event() = default;
 
+        /// Number of blocks per term
+        const TermDuration: T::BlockNumber = T::TermDuration::get();
+
+        /// Number of blocks between CRL fetches of the offchain worker
+        const CrlFetchInterval: T::BlockNumber = T::CrlFetchInterval::get();
+
+        fn on_initialize(n: T::BlockNumber) {
+            Self::migrate_storage();
+            let duration = T::TermDuration::get();
+            if !duration.is_zero() && !n.is_zero() && (n % duration).is_zero() {
+                Self::advance_term(n);
+            }
+        }
+
+        fn on_finalize() {
+            // sums over every account, so only in debug builds
+            if StorageVersion::get() >= STORAGE_VERSION {
+                debug_assert_eq!(
+                    Self::total_balance(),
+                    Self::total_issuance(),
+                    "TotalIssuance is off from the sum of balances"
+                );
+            }
+        }
+
+        /// Mynachain txs are authorized by their RSA signature, so they come in unsigned
+        /// and are checked by `validate_unsigned` before reaching the pool
+        #[weight = weights::TxWeight]
         pub fn go(origin, tx: types::SignedData) -> DispatchResult{
+            ensure_none(origin)?;
+            Self::ensure_migrated()?;
             match tx.clone().tbs {
                 types::Tx::CreateAccount(t) => Self::create_account(tx, t),
                 types::Tx::Send(t) => Self::send(tx, t),
                 types::Tx::Mint(t) => Self::mint(tx, t),
                 types::Tx::Vote(t) => Self::vote(tx, t),
                 types::Tx::Write(t) => Self::write(tx, t),
-                types::Tx::NextTerm(t) => Self::next_term(tx,t),
+                types::Tx::MigrateAccount(t) => Self::migrate_account(tx, t),
+                types::Tx::Withdraw(t) => Self::withdraw(tx, t),
+                types::Tx::LinkKey(t) => Self::link_key(tx, t),
+                types::Tx::UnlinkKey(t) => Self::unlink_key(tx, t),
+                types::Tx::NextTerm(_) => Err(Error::<T>::NextTermByTx.into()),
                 _ => Ok(())
             }
         }
+
+        /// Move `amount` of the sender's Balances funds to the mynachain account `to`
+        pub fn deposit(origin, to: types::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::ensure_migrated()?;
+            let value = Self::from_currency(amount)?;
+            ensure!(value > 0, Error::<T>::InvalidAmount);
+            let mut recipient = Self::settle(to)?;
+            // a card that can't sign could never move the funds on
+            let account = Accounts::get(to);
+            ensure!(!Self::is_frozen(&account), Error::<T>::AccountRevoked);
+            ensure!(!Self::is_expired(&account), Error::<T>::AccountExpired);
+            recipient.free = recipient.free.checked_add(value).ok_or(Error::<T>::Overflow)?;
+            let issuance = TotalIssuance::get().checked_add(value).ok_or(Error::<T>::Overflow)?;
+
+            // dropping the imbalance burns it on the Balances side
+            let _ = <balances::Module<T> as Currency<_>>::withdraw(
+                &who,
+                amount,
+                WithdrawReason::Transfer.into(),
+                ExistenceRequirement::KeepAlive,
+            )?;
+            Ledgers::insert(to, recipient);
+            TotalIssuance::put(issuance);
+            Self::deposit_balances_event(&who);
+            Self::deposit_event(RawEvent::Deposited(who, to, value));
+            Ok(())
+        }
+
+        /// Send from the account the signing key is linked to, within the key's limit
+        pub fn linked_send(origin, to: types::AccountId, amount: types::Balance) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let mut key = Self::ensure_linked(&who)?;
+            let spent = key.spent.checked_add(amount).ok_or(Error::<T>::Overflow)?;
+            match key.scope {
+                types::KeyScope::Spend { limit } => ensure!(spent <= limit, Error::<T>::SendLimitExceeded),
+                types::KeyScope::WriteOnly => return Err(Error::<T>::KeyNotPermitted.into()),
+            }
+            Self::transfer(key.account, to, amount)?;
+            key.spent = spent;
+            <LinkedKeys<T>>::insert(who, key);
+            Ok(())
+        }
+
+        /// Accept the link `account` offered to the signing key with `TxLinkKey`
+        pub fn accept_key(origin, account: types::AccountId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let offer = <KeyOffers<T>>::get(&who, account).ok_or(Error::<T>::KeyNotOffered)?;
+            ensure!(Self::term_number() <= offer.expires_at, Error::<T>::KeyExpired);
+            if let Ok(linked) = Self::ensure_linked(&who) {
+                ensure!(linked.account == account, Error::<T>::KeyInUse);
+            }
+
+            <KeyOffers<T>>::remove(&who, account);
+            let expires_at = offer.expires_at;
+            <LinkedKeys<T>>::insert(&who, offer);
+            Self::deposit_event(RawEvent::KeyLinked(account, who, expires_at));
+            Ok(())
+        }
+
+        /// Drop the link of the signing key, e.g. one left behind by an expired or revoked card
+        pub fn release_key(origin) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let linked = Self::linked_key(&who).ok_or(Error::<T>::KeyNotLinked)?;
+            <LinkedKeys<T>>::remove(&who);
+            Self::deposit_event(RawEvent::KeyUnlinked(linked.account, who));
+            Ok(())
+        }
+
+        /// `write` for the account the signing key is linked to
+        pub fn linked_write(origin, data: Vec<u8>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let from = Self::ensure_linked(&who)?.account;
+            ensure!(data.len() <= MAX_DATA_LEN, Error::<T>::DataTooLarge);
+            Accounts::mutate(from, |account| account.data = data);
+            Self::deposit_event(RawEvent::Written(from));
+            Ok(())
+        }
+
+        /// Allow `id` to mint up to `cap_per_term` per term
+        pub fn add_issuer(origin, id: types::AccountId, cap_per_term: types::Balance) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(Accounts::exists(id), Error::<T>::AccountNotFound);
+            ensure!(cap_per_term >= 0, Error::<T>::NegativeMintCap);
+            Issuers::insert(id, types::Issuer { cap_per_term });
+            Self::deposit_event(RawEvent::IssuerAdded(id, cap_per_term));
+            Ok(())
+        }
+
+        /// Emergency switch to the next term ahead of schedule
+        pub fn force_next_term(origin) -> DispatchResult {
+            ensure_root(origin)?;
+            Self::advance_term(<system::Module<T>>::block_number());
+            Ok(())
+        }
+
+        /// Trust `cert` from block `active_from` on; a future block schedules it
+        pub fn add_ca(origin, cert: Vec<u8>, active_from: T::BlockNumber) -> DispatchResult {
+            ensure_root(origin)?;
+            let id = Blake2Hasher::hash(&cert[..]);
+            ensure!(!<TrustedCas<T>>::exists(id), Error::<T>::CaAlreadyExists);
+            ensure!(
+                Self::unretired_cas() < weights::MAX_ACTIVE_CAS,
+                Error::<T>::TooManyCas
+            );
+            Self::insert_ca(cert, active_from);
+            Self::deposit_event(RawEvent::CaAdded(id, active_from));
+            Ok(())
+        }
+
+        /// Stop trusting a CA at block `at`; accounts it already vouched for are kept
+        pub fn retire_ca(origin, id: types::CaId, at: T::BlockNumber) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(<TrustedCas<T>>::exists(id), Error::<T>::CaNotFound);
+            // moving it later would bring a CA back without counting it against `add_ca`'s cap
+            ensure!(
+                Self::trusted_ca(id).retired_at.map_or(true, |retired_at| at < retired_at),
+                Error::<T>::CaAlreadyRetired
+            );
+            <TrustedCas<T>>::mutate(id, |ca| ca.retired_at = Some(at));
+            Self::deposit_event(RawEvent::CaRetired(id, at));
+            Ok(())
+        }
+
+        /// Forget a retired CA. CRLs it signs are no longer accepted; what they revoked stays revoked.
+        pub fn remove_ca(origin, id: types::CaId) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(<TrustedCas<T>>::exists(id), Error::<T>::CaNotFound);
+            let block = <system::Module<T>>::block_number();
+            ensure!(
+                Self::trusted_ca(id).retired_at.map_or(false, |retired_at| retired_at <= block),
+                Error::<T>::CaNotRetired
+            );
+            <TrustedCas<T>>::remove(id);
+            Self::deposit_event(RawEvent::CaRemoved(id));
+            Ok(())
+        }
+
+        pub fn remove_issuer(origin, id: types::AccountId) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(Issuers::exists(id), Error::<T>::NotAnIssuer);
+            Issuers::remove(id);
+            Self::deposit_event(RawEvent::IssuerRemoved(id));
+            Ok(())
+        }
+
+        /// Let `oracle` submit CRLs too, or nobody but root with `None`
+        pub fn set_crl_oracle(origin, oracle: Option<T::AccountId>) -> DispatchResult {
+            ensure_root(origin)?;
+            <CrlOracle<T>>::set(oracle.clone());
+            Self::deposit_event(RawEvent::CrlOracleSet(oracle));
+            Ok(())
+        }
+
+        /// Revoke the serials of a DER CRL signed by a trusted CA. Revocations are never lifted.
+        #[weight = weights::CrlWeight]
+        pub fn update_crl(origin, crl: Vec<u8>) -> DispatchResult {
+            Self::ensure_root_or_oracle(origin)?;
+            Self::apply_crl(&crl[..])
+        }
+
+        /// Same as `update_crl`, from the offchain worker. The CRL signature is the authorization.
+        #[weight = weights::CrlWeight]
+        pub fn submit_crl(origin, crl: Vec<u8>) -> DispatchResult {
+            ensure_none(origin)?;
+            Self::apply_crl(&crl[..])
+        }
+
+        fn offchain_worker(now: T::BlockNumber) {
+            let interval = T::CrlFetchInterval::get();
+            if !interval.is_zero() && (now % interval).is_zero() {
+                match Self::fetch_crl() {
+                    // no URL configured: CRL fetching is off
+                    Ok(()) | Err(Error::CrlUrlNotSet) => {}
+                    Err(e) => debug::print(<&'static str>::from(e)),
+                }
+            }
+        }
     }
 }
 
@@ -69,133 +487,731 @@ impl<T: Trait> Module<T> {
     /// nonce must be zero
     /// id must be zero
     pub fn create_account(tx: types::SignedData, tbs: types::TxCreateAccount) -> DispatchResult {
-        ensure!(tbs.nonce == 0, "Nonce is not zero");
-
-        tbs.check_ca()?;
-
-        let sig = &tx.signature;
-        let pubkey = crypto::extract_pubkey(&tbs.cert[..]).map_err(|_| "failed to get pubkey")?;
-        tx.verify(pubkey)?;
-        Self::insert_account(tbs.cert)?;
+        let account = Self::ensure_cert_signed(&tx, &tbs)?;
+        ensure!(!Self::has_legacy_account(&tbs.cert[..]), Error::<T>::CertificateInUse);
+        Self::insert_account(account)?;
         Ok(())
     }
 
     pub fn send(tx: types::SignedData, tbs: types::TxSend) -> DispatchResult {
-        let to = tbs.to;
         let from = Self::ensure_rsa_signed(&tx)?;
-        let amount = tbs.amount;
-        Self::transfer(from, to, amount)?;
         Self::increment_nonce(from)?;
-        Ok(())
+        Self::transfer(from, tbs.to, tbs.amount)
     }
     pub fn mint(tx: types::SignedData, tbs: types::TxMint) -> DispatchResult {
         let from = Self::ensure_rsa_signed(&tx)?;
-        let amount = tbs.amount;
-        let pre_bal = RawBalance::get(from);
-        let new_bal = pre_bal.checked_add(amount).ok_or("overflow")?;
-        RawBalance::insert(from, new_bal);
         Self::increment_nonce(from)?;
-        Self::deposit_event(Event::Minted(from, amount));
-
-        Ok(())
+        Self::issue(from, tbs.to, tbs.amount)
     }
     pub fn vote(tx: types::SignedData, tbs: types::TxVote) -> DispatchResult {
         let from = Self::ensure_rsa_signed(&tx)?;
-        let amount = tbs.amount;
-        let term = Self::term_number();
-        let pre_bal = CumulativeVotes::get(term as u32);
-        let new_bal = pre_bal.checked_add(amount).ok_or("overflow")?;
-        ensure!(new_bal <= MAX_VOTE_BALANCE_PER_TERM, "too large amount");
-
-        CumulativeVotes::insert(term, new_bal);
         Self::increment_nonce(from)?;
-        Self::deposit_event(Event::Voted(from, amount));
-
-        Ok(())
+        Self::cast_vote(from, tbs.amount)
     }
-    pub fn next_term(tx: types::SignedData, tbs: types::TxNextTerm) -> DispatchResult {
-        let from = Self::ensure_rsa_signed(&tx)?;
+    /// Close the current term and carry its final votes over
+    pub fn advance_term(now: T::BlockNumber) {
         let cur_term = Self::term_number();
         let new_term = cur_term + 1;
-        
+
         let final_votes = CumulativeVotes::get(cur_term);
         CumulativeVotes::insert(new_term, final_votes);
-        
+        EligibleAccounts::put(AccountCount::get());
+
         TermNumber::put(new_term);
-        Self::deposit_event(Event::NextTerm(new_term));
+        Self::deposit_event(RawEvent::NextTerm(new_term, now));
+    }
+
+    /// Move an account to a renewed card. The account keeps its id, which stays the
+    /// holder's id for life, along with its balance, data and nonce; only the cert changes.
+    /// The old card may have expired, but not been revoked.
+    pub fn migrate_account(tx: types::SignedData, tbs: types::TxMigrateAccount) -> DispatchResult {
+        ensure!(Accounts::exists(tx.id), Error::<T>::AccountNotFound);
+        let id = Self::ensure_card_holder(&Accounts::get(tx.id), &tx)?;
+        Self::increment_nonce(id)?;
+        let renewed = Self::check_renewed_cert(&tbs)?;
+
+        let mut account = Accounts::get(id);
+        account.pubkey = renewed.pubkey;
+        account.fingerprint = renewed.fingerprint;
+        account.expires_at = renewed.expires_at;
+        account.issuer = renewed.issuer;
+        account.serial = renewed.serial;
+        Accounts::insert(id, account);
+        MigratedCerts::insert(renewed.id, id);
+        Self::deposit_event(RawEvent::AccountMigrated(id, renewed.id));
+        Ok(())
+    }
+
+    /// The other half of `deposit`: move funds out to an account of the Balances pallet
+    pub fn withdraw(tx: types::SignedData, tbs: types::TxWithdraw) -> DispatchResult {
+        let from = Self::ensure_rsa_signed(&tx)?;
+        Self::increment_nonce(from)?;
+        let to = T::AccountId::decode(&mut &tbs.to[..]).map_err(|_| Error::<T>::InvalidDestination)?;
+        ensure!(tbs.amount > 0, Error::<T>::InvalidAmount);
+        let amount = Self::to_currency(tbs.amount)?;
+
+        let mut sender = Self::settle(from)?;
+        ensure!(sender.free >= tbs.amount, Error::<T>::InsufficientBalance);
+        sender.free -= tbs.amount;
+        // `deposit_creating` drops what can't open an account
+        ensure!(
+            amount >= <balances::Module<T> as Currency<_>>::minimum_balance()
+                || !<balances::Module<T> as Currency<_>>::total_balance(&to).is_zero(),
+            Error::<T>::BelowExistentialDeposit
+        );
+
+        Ledgers::insert(from, sender);
+        TotalIssuance::mutate(|issuance| *issuance -= tbs.amount);
+        let _ = <balances::Module<T> as Currency<_>>::deposit_creating(&to, amount);
+        Self::deposit_balances_event(&to);
+        Self::deposit_event(RawEvent::Withdrawn(from, to, tbs.amount));
+        Ok(())
+    }
+
+    /// `Currency::withdraw` and `deposit_creating` emit nothing, so report the new balance
+    /// of `who` on the Balances side the way `set_balance` does
+    fn deposit_balances_event(who: &T::AccountId) {
+        let free = <balances::Module<T>>::free_balance(who);
+        let reserved = <balances::Module<T>>::reserved_balance(who);
+        let event: <T as balances::Trait>::Event =
+            balances::Event::<T>::BalanceSet(who.clone(), free, reserved).into();
+        <system::Module<T>>::deposit_event(event.into());
+    }
+
+    /// Offer to let a key of the runtime act for the signer, see `linked_send` and
+    /// `linked_write`. The link only takes effect once the key calls `accept_key`.
+    pub fn link_key(tx: types::SignedData, tbs: types::TxLinkKey) -> DispatchResult {
+        let from = Self::ensure_rsa_signed(&tx)?;
+        Self::increment_nonce(from)?;
+        let key = T::AccountId::decode(&mut &tbs.key[..]).map_err(|_| Error::<T>::InvalidKey)?;
+        ensure!(tbs.expires_at >= Self::term_number(), Error::<T>::KeyExpired);
+        if let Ok(linked) = Self::ensure_linked(&key) {
+            ensure!(linked.account == from, Error::<T>::KeyInUse);
+        }
+
+        <KeyOffers<T>>::insert(
+            &key,
+            from,
+            types::LinkedKey {
+                account: from,
+                scope: tbs.scope,
+                spent: 0,
+                expires_at: tbs.expires_at,
+            },
+        );
+        Self::deposit_event(RawEvent::KeyOffered(from, key, tbs.expires_at));
+        Ok(())
+    }
+
+    pub fn unlink_key(tx: types::SignedData, tbs: types::TxUnlinkKey) -> DispatchResult {
+        let from = Self::ensure_rsa_signed(&tx)?;
+        Self::increment_nonce(from)?;
+        let key = T::AccountId::decode(&mut &tbs.key[..]).map_err(|_| Error::<T>::InvalidKey)?;
+        let offered = <KeyOffers<T>>::exists(&key, from);
+        let linked = Self::linked_key(&key).map_or(false, |linked| linked.account == from);
+        ensure!(offered || linked, Error::<T>::KeyNotLinked);
+
+        <KeyOffers<T>>::remove(&key, from);
+        if linked {
+            <LinkedKeys<T>>::remove(&key);
+        }
+        Self::deposit_event(RawEvent::KeyUnlinked(from, key));
         Ok(())
     }
 
+    /// The link of `key`, if it may act for its account now. A key can do no more than
+    /// its card: it stops working when the card expires or is revoked.
+    pub fn ensure_linked(key: &T::AccountId) -> Result<types::LinkedKey, DispatchError> {
+        Self::ensure_migrated()?;
+        let linked = Self::linked_key(key).ok_or(Error::<T>::KeyNotLinked)?;
+        ensure!(Self::term_number() <= linked.expires_at, Error::<T>::KeyExpired);
+        ensure!(Accounts::exists(linked.account), Error::<T>::AccountNotFound);
+        let account = Accounts::get(linked.account);
+        ensure!(!Self::is_frozen(&account), Error::<T>::AccountRevoked);
+        ensure!(!Self::is_expired(&account), Error::<T>::AccountExpired);
+        Ok(linked)
+    }
+
     pub fn write(tx: types::SignedData, tbs: types::TxWrite) -> DispatchResult {
         let from = Self::ensure_rsa_signed(&tx)?;
-        let mut account = Accounts::get(from);
-        account.data = tbs.data;
-        Accounts::insert(from, account);
         Self::increment_nonce(from)?;
-        Self::deposit_event(Event::Written(from));
+        ensure!(tbs.data.len() <= MAX_DATA_LEN, Error::<T>::DataTooLarge);
+        Accounts::mutate(from, |account| account.data = tbs.data);
+        Self::deposit_event(RawEvent::Written(from));
         Ok(())
     }
 }
+impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
+    type Call = Call<T>;
+
+    fn validate_unsigned(call: &Self::Call) -> TransactionValidity {
+        match call {
+            Call::go(tx) => Self::validate_tx(tx),
+            Call::submit_crl(crl) => Self::validate_crl(crl),
+            _ => InvalidTransaction::Call.into(),
+        }
+    }
+}
+
 // module func starts here
 impl<T: Trait> Module<T> {
-    pub fn insert_account(cert: Vec<u8>) -> DispatchResult {
-        let new_account_id = Blake2Hasher::hash(&cert[..]);
+    pub fn insert_account(new_account: types::Account) -> DispatchResult {
+        let new_account_id = new_account.id;
 
-        ensure!(!Accounts::exists(new_account_id), "Account already exists");
+        ensure!(!Accounts::exists(new_account_id), Error::<T>::AccountAlreadyExists);
+        ensure!(!MigratedCerts::exists(new_account_id), Error::<T>::CertificateInUse);
 
         let new_count = AccountCount::get();
 
-        let new_account = types::Account {
-            cert,
-            id: new_account_id,
-            nonce: 0,
-            data: vec![],
-            created_at: Self::term_number()
-        };
         Accounts::insert(new_account_id, new_account);
         AccountEnumerator::insert(new_count, new_account_id);
         AccountCount::mutate(|t| *t += 1);
 
-        Self::deposit_event(Event::AccountAdd(new_account_id));
+        Self::deposit_event(RawEvent::AccountAdd(new_account_id));
 
         Ok(())
     }
-    pub fn ensure_rsa_signed(tx: &types::SignedData) -> Result<types::AccountId, &'static str> {
-        ensure!(Accounts::exists(tx.id), "Account not found");
+    /// The signer of `tx`. Callers spend the nonce right after this, before anything
+    /// else can fail, so a tx that fails can't be replayed once it would succeed.
+    pub fn ensure_rsa_signed(tx: &types::SignedData) -> Result<types::AccountId, DispatchError> {
+        ensure!(Accounts::exists(tx.id), Error::<T>::AccountNotFound);
         let account = Accounts::get(tx.id);
-        let pubkey =
-            crypto::extract_pubkey(&account.cert[..]).map_err(|_| "failed to get pubkey")?;
-        tx.verify(pubkey)?;
+        ensure!(!Self::is_expired(&account), Error::<T>::AccountExpired);
+        Self::ensure_card_holder(&account, tx)
+    }
+    /// `ensure_rsa_signed` minus the expiry check, so an expired card can still migrate
+    fn ensure_card_holder(account: &types::Account, tx: &types::SignedData) -> Result<types::AccountId, DispatchError> {
+        ensure!(!Self::is_frozen(account), Error::<T>::AccountRevoked);
+        Self::check_nonce(account, &tx.tbs)?;
+        Self::check_signature(account, tx)?;
         Ok(account.id)
     }
+    /// Current time in unix seconds
+    pub fn now() -> u64 {
+        <timestamp::Module<T>>::now().saturated_into::<u64>() / 1000
+    }
+    pub fn is_expired(account: &types::Account) -> bool {
+        Self::now() > account.expires_at
+    }
+    /// Accounts whose cert was revoked can't sign anything any more
+    pub fn is_frozen(account: &types::Account) -> bool {
+        Revoked::get(account.issuer, &account.serial)
+    }
+    pub fn account_status(id: types::AccountId) -> Option<types::AccountStatus> {
+        let account = Self::account_of(id)?;
+        Some(if Self::is_frozen(&account) {
+            types::AccountStatus::Revoked
+        } else if Self::is_expired(&account) {
+            types::AccountStatus::AccountExpired
+        } else {
+            types::AccountStatus::Active
+        })
+    }
+    pub fn check_signature(account: &types::Account, tx: &types::SignedData) -> DispatchResult {
+        tx.verify(&account.pubkey, &Self::signing_context())
+            .map_err(|e| Error::<T>::from(e).into())
+    }
+    /// A `CreateAccount` is signed by the cert it registers.
+    /// Returns the account it would create.
+    pub fn ensure_cert_signed(
+        tx: &types::SignedData,
+        tbs: &types::TxCreateAccount,
+    ) -> Result<types::Account, DispatchError> {
+        ensure!(tbs.nonce == 0, Error::<T>::NonceNotZero);
+
+        // anyone can pick up a cert, so its signature on the tx comes before the CA checks
+        let info = x509::parse_cert(&tbs.cert[..]).map_err(|_| Error::<T>::InvalidCertificate)?;
+        let pubkey = types::RsaPublicKey::from_der(info.public_key).map_err(Error::<T>::from)?;
+        tx.verify(&pubkey, &Self::signing_context())
+            .map_err(Error::<T>::from)?;
+        Self::account_for_cert(&tbs.cert[..])
+    }
+    /// The renewed cert of a migration must be fit to create an account, unused,
+    /// and must have signed the migration
+    pub fn check_renewed_cert(tbs: &types::TxMigrateAccount) -> Result<types::Account, DispatchError> {
+        let renewed = Self::account_for_cert(&tbs.new_cert[..])?;
+        ensure!(
+            !Accounts::exists(renewed.id)
+                && !MigratedCerts::exists(renewed.id)
+                && !Self::has_legacy_account(&tbs.new_cert[..]),
+            Error::<T>::CertificateInUse
+        );
+        tbs.verify_new_signature(&renewed.pubkey, &Self::signing_context())
+            .map_err(Error::<T>::from)?;
+        Ok(renewed)
+    }
+    /// Accounts created before ids were salted are keyed by the bare `Blake2(cert)`,
+    /// and so are the certs migrated to before then. Those ids stay as they are.
+    fn has_legacy_account(cert: &[u8]) -> bool {
+        let legacy = Blake2Hasher::hash(cert);
+        Accounts::exists(legacy) || MigratedCerts::exists(legacy)
+    }
+    /// A fresh account for a cert issued by an active CA, valid now and not revoked.
+    /// Its id is the fingerprint, so it can't be matched against the cert either.
+    fn account_for_cert(cert: &[u8]) -> Result<types::Account, DispatchError> {
+        let tbs = types::TxCreateAccount {
+            cert: cert.to_vec(),
+            nonce: 0,
+        };
+        let issuer = tbs.check_ca(&Self::active_cas()).map_err(Error::<T>::from)?;
+        let info = x509::parse_cert(cert).map_err(|_| Error::<T>::InvalidCertificate)?;
+        let now = Self::now();
+        ensure!(now >= info.not_before, Error::<T>::CertificateNotYetValid);
+        ensure!(now <= info.not_after, Error::<T>::CertificateExpired);
+        ensure!(!Revoked::get(issuer, info.serial.to_vec()), Error::<T>::CertificateRevoked);
+
+        let fingerprint = Self::fingerprint(cert);
+        Ok(types::Account {
+            pubkey: types::RsaPublicKey::from_der(info.public_key).map_err(Error::<T>::from)?,
+            fingerprint,
+            id: fingerprint,
+            nonce: 0,
+            data: vec![],
+            created_at: Self::term_number(),
+            expires_at: info.not_after,
+            issuer,
+            serial: info.serial.to_vec(),
+        })
+    }
+
+    /// Pool-side check of a tx: signature and nonce, plus `(AccountId, nonce)` tags
+    /// so that txs of one account are ordered and replaced correctly.
+    pub fn validate_tx(tx: &types::SignedData) -> TransactionValidity {
+        if StorageVersion::get() < STORAGE_VERSION {
+            return UnknownTransaction::CannotLookup.into();
+        }
+        let (id, nonce, account_nonce) = match &tx.tbs {
+            types::Tx::Other | types::Tx::NextTerm(_) => return InvalidTransaction::Call.into(),
+            types::Tx::Write(tbs) if tbs.data.len() > MAX_DATA_LEN => {
+                return InvalidTransaction::ExhaustsResources.into()
+            }
+            types::Tx::CreateAccount(tbs) => {
+                let id = Self::ensure_cert_signed(tx, tbs)
+                    .map_err(|_| InvalidTransaction::BadProof)?
+                    .id;
+                if Accounts::exists(id)
+                    || MigratedCerts::exists(id)
+                    || Self::has_legacy_account(&tbs.cert[..])
+                {
+                    return InvalidTransaction::Stale.into();
+                }
+                (id, 0, 0)
+            }
+            tbs => {
+                if !Accounts::exists(tx.id) {
+                    return UnknownTransaction::CannotLookup.into();
+                }
+                let account = Accounts::get(tx.id);
+                let migration = match tbs {
+                    types::Tx::MigrateAccount(migration) => Some(migration),
+                    _ => None,
+                };
+                if Self::is_expired(&account) && migration.is_none() {
+                    return InvalidTransaction::Custom(ACCOUNT_EXPIRED).into();
+                }
+                if Self::is_frozen(&account) {
+                    return InvalidTransaction::Custom(ACCOUNT_REVOKED).into();
+                }
+                let nonce = tbs.nonce().ok_or(InvalidTransaction::Call)?;
+                if nonce < account.nonce {
+                    return InvalidTransaction::Stale.into();
+                }
+                Self::check_signature(&account, tx).map_err(|_| InvalidTransaction::BadProof)?;
+                if let Some(migration) = migration {
+                    Self::check_renewed_cert(migration).map_err(|_| InvalidTransaction::BadProof)?;
+                }
+                (account.id, nonce, account.nonce)
+            }
+        };
+
+        let requires = if nonce > account_nonce {
+            vec![(id, nonce - 1).encode()]
+        } else {
+            vec![]
+        };
+        Ok(ValidTransaction {
+            priority: 0,
+            requires,
+            provides: vec![(id, nonce).encode()],
+            longevity: TransactionLongevity::max_value(),
+            propagate: true,
+        })
+    }
 
     pub fn transfer(
         from: types::AccountId,
         to: types::AccountId,
         amount: types::Balance,
     ) -> DispatchResult {
-        ensure!(Accounts::exists(from), "Account not found");
-        ensure!(Accounts::exists(to), "Account not found");
-
-        let new_compbal_from = Self::compute_balance(from)?
-            .checked_sub(amount)
-            .ok_or("underflow")?;
-        ensure!(new_compbal_from >= 0, "Insufficient Balance");
-        Self::compute_balance(to)?
+        ensure!(amount > 0, Error::<T>::InvalidAmount);
+        let mut sender = Self::settle(from)?;
+        let mut recipient = Self::settle(to)?;
+        ensure!(sender.free >= amount, Error::<T>::InsufficientBalance);
+        sender.free -= amount;
+        recipient.free = recipient.free.checked_add(amount).ok_or(Error::<T>::Overflow)?;
+
+        // sending to oneself changes nothing
+        if from != to {
+            Ledgers::insert(from, sender);
+            Ledgers::insert(to, recipient);
+        }
+        Self::deposit_event(RawEvent::Transferred(from, to, amount));
+        Ok(())
+    }
+
+    pub fn insert_ca(cert: Vec<u8>, active_from: T::BlockNumber) {
+        let id = Blake2Hasher::hash(&cert[..]);
+        <TrustedCas<T>>::insert(
+            id,
+            types::CaRecord {
+                cert,
+                active_from,
+                retired_at: None,
+            },
+        );
+    }
+
+    /// Number of CAs trusted now or from a later block, and not retired yet
+    fn unretired_cas() -> usize {
+        let block = <system::Module<T>>::block_number();
+        <TrustedCas<T>>::enumerate()
+            .filter(|(_, ca)| ca.retired_at.as_ref().map_or(true, |at| block < *at))
+            .count()
+    }
+
+    /// DER certs of the CAs trusted at the current block and within their own validity
+    pub fn active_cas() -> Vec<Vec<u8>> {
+        let block = <system::Module<T>>::block_number();
+        let now = Self::now();
+        <TrustedCas<T>>::enumerate()
+            .filter(|(_, ca)| ca.is_active(&block))
+            .filter(|(_, ca)| {
+                x509::parse_cert(&ca.cert[..]).map_or(false, |info| info.check_validity(now).is_ok())
+            })
+            .map(|(_, ca)| ca.cert)
+            .collect()
+    }
+
+    fn ensure_root_or_oracle(origin: T::Origin) -> DispatchResult {
+        let origin: Result<system::RawOrigin<T::AccountId>, T::Origin> = origin.into();
+        match origin {
+            Ok(system::RawOrigin::Root) => Ok(()),
+            Ok(system::RawOrigin::Signed(who)) if Some(who.clone()) == Self::crl_oracle() => Ok(()),
+            _ => Err(Error::<T>::NotCrlOracle.into()),
+        }
+    }
+
+    /// Fetch the CRL at the URL in offchain storage and submit it if it revokes anything new
+    pub fn fetch_crl() -> Result<(), Error<T>> {
+        let url = sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, CRL_URL_KEY)
+            .ok_or(Error::<T>::CrlUrlNotSet)?;
+        let url = core::str::from_utf8(&url[..]).map_err(|_| Error::<T>::CrlUrlNotUtf8)?;
+
+        let deadline = sp_io::offchain::timestamp().add(Duration::from_millis(CRL_FETCH_TIMEOUT_MS));
+        let pending = http::Request::get(url)
+            .deadline(deadline)
+            .send()
+            .map_err(|_| Error::<T>::CrlRequestFailed)?;
+        let response = pending
+            .try_wait(deadline)
+            .map_err(|_| Error::<T>::CrlRequestTimedOut)?
+            .map_err(|_| Error::<T>::CrlRequestFailed)?;
+        ensure!(response.code == 200, Error::<T>::CrlRequestFailed);
+        // one byte past the limit is enough to tell it's too large
+        let crl = response.body().take(weights::MAX_CRL_LEN + 1).collect::<Vec<u8>>();
+        ensure!(crl.len() <= weights::MAX_CRL_LEN, Error::<T>::CrlTooLarge);
+
+        let info = x509::parse_crl(&crl[..]).map_err(|_| Error::<T>::InvalidCrl)?;
+        let issuer = Self::crl_issuer(&info).ok_or(Error::<T>::UntrustedCrl)?;
+        if !Self::revokes_anything_new(issuer, &info) {
+            return Ok(());
+        }
+        T::SubmitTransaction::submit_unsigned(Call::submit_crl(crl))
+            .map_err(|_| Error::<T>::CrlSubmitFailed)
+    }
+
+    /// Pool-side check of a CRL from the offchain worker
+    pub fn validate_crl(crl: &[u8]) -> TransactionValidity {
+        if crl.len() > weights::MAX_CRL_LEN {
+            return InvalidTransaction::ExhaustsResources.into();
+        }
+        let info = x509::parse_crl(crl).map_err(|_| InvalidTransaction::Call)?;
+        let issuer = Self::crl_issuer(&info).ok_or(InvalidTransaction::BadProof)?;
+        if !Self::revokes_anything_new(issuer, &info) {
+            return InvalidTransaction::Stale.into();
+        }
+        Ok(ValidTransaction {
+            priority: 0,
+            requires: vec![],
+            provides: vec![(&b"crl"[..], Blake2Hasher::hash(crl)).encode()],
+            longevity: TransactionLongevity::max_value(),
+            propagate: true,
+        })
+    }
+
+    fn revokes_anything_new(issuer: types::CaId, crl: &x509::CrlInfo) -> bool {
+        crl.revoked
+            .iter()
+            .any(|serial| !Revoked::get(issuer, serial.to_vec()))
+    }
+
+    /// Check a DER CRL against the trusted CAs and record its serials as revoked
+    pub fn apply_crl(crl: &[u8]) -> DispatchResult {
+        ensure!(crl.len() <= weights::MAX_CRL_LEN, Error::<T>::CrlTooLarge);
+        let crl = x509::parse_crl(crl).map_err(|_| Error::<T>::InvalidCrl)?;
+        let issuer = Self::crl_issuer(&crl).ok_or(Error::<T>::UntrustedCrl)?;
+        for serial in crl.revoked.iter() {
+            Revoked::insert(issuer, serial.to_vec(), true);
+        }
+        Self::deposit_event(RawEvent::CrlUpdated(issuer, crl.revoked.len() as u32));
+        Ok(())
+    }
+
+    /// The trusted CA named as the CRL issuer whose key signed it.
+    /// Retired CAs count: the certs they issued are still around.
+    pub fn crl_issuer(crl: &x509::CrlInfo) -> Option<types::CaId> {
+        let digest = sp_io::hashing::sha2_256(crl.tbs);
+        <TrustedCas<T>>::enumerate()
+            .find(|(_, ca)| {
+                x509::parse_cert(&ca.cert[..]).map_or(false, |info| {
+                    info.subject == crl.issuer
+                        && types::RsaPublicKey::from_der(info.public_key)
+                            .and_then(|key| key.verify(&digest[..], crl.signature))
+                            .is_ok()
+                })
+            })
+            .map(|(id, _)| id)
+    }
+
+    /// Salted with the genesis hash, so it can't be matched against certs collected elsewhere.
+    /// Also the id of accounts created since; older ones keep their `Blake2(cert)` id.
+    pub fn fingerprint(cert: &[u8]) -> H256 {
+        Self::signing_context().fingerprint(cert)
+    }
+
+    /// Accounts can't be read while some are still in an older layout
+    fn ensure_migrated() -> DispatchResult {
+        ensure!(StorageVersion::get() >= STORAGE_VERSION, Error::<T>::MigrationPending);
+        Ok(())
+    }
+
+    /// Bring storage written by older runtimes up to `STORAGE_VERSION`, `MIGRATION_BATCH`
+    /// accounts per block. Until the last batch, `ensure_migrated` holds off everything
+    /// that reads accounts.
+    pub fn migrate_storage() {
+        let version = StorageVersion::get();
+        if version >= STORAGE_VERSION {
+            return;
+        }
+        let start = MigrationCursor::get();
+        if start == 0 {
+            if version < 1 {
+                Self::seed_trusted_cas();
+            }
+            EligibleAccounts::put(0);
+            TotalIssuance::put(0);
+        }
+        // every CA ever trusted, since an old account's may have been retired since
+        let cas: Vec<Vec<u8>> = if version < 1 {
+            <TrustedCas<T>>::enumerate().map(|(_, ca)| ca.cert).collect()
+        } else {
+            vec![]
+        };
+        let term = Self::term_number();
+        let end = AccountCount::get().min(start.saturating_add(MIGRATION_BATCH));
+        for i in start..end {
+            let id = AccountEnumerator::get(i);
+            if version < 2 {
+                let key = Accounts::hashed_key_for(id);
+                let account = match version {
+                    0 => unhashed::get::<types::AccountWithCert>(&key)
+                        .map(|old| Self::account_from_v0(old, &cas)),
+                    _ => unhashed::get::<types::AccountV1>(&key).map(Self::account_from_v1),
+                };
+                if let Some(account) = account {
+                    Accounts::insert(id, account);
+                }
+            }
+            if version < 3 {
+                Self::ledger_from_raw_balance(id);
+            }
+            // a term that ends mid-migration already counts every account
+            if Accounts::get(id).created_at < term {
+                let count = AccountCount::get();
+                EligibleAccounts::mutate(|eligible| *eligible = (*eligible + 1).min(count));
+            }
+            let balance = Self::compute_balance(id).unwrap_or_default();
+            TotalIssuance::mutate(|issuance| *issuance += balance);
+        }
+        if end < AccountCount::get() {
+            MigrationCursor::put(end);
+            return;
+        }
+        MigrationCursor::kill();
+        StorageVersion::put(STORAGE_VERSION);
+    }
+
+    /// Trust the JPKI CAs the runtime trusted before `TrustedCas` existed, as genesis does
+    fn seed_trusted_cas() {
+        for cert in certs::auth_ca.iter() {
+            if !<TrustedCas<T>>::exists(Blake2Hasher::hash(cert)) {
+                Self::insert_ca(cert.to_vec(), Zero::zero());
+            }
+        }
+    }
+
+    /// Strip the cert, keeping what the account needs from it: the decoded public key,
+    /// the fingerprint, notAfter, the serial, and which of `cas` issued it.
+    /// A cert that no longer parses leaves an account that is expired from the start.
+    fn account_from_v0(old: types::AccountWithCert, cas: &[Vec<u8>]) -> types::Account {
+        let info = x509::parse_cert(&old.cert[..]).ok();
+        let issuer = types::TxCreateAccount {
+            cert: old.cert.clone(),
+            nonce: 0,
+        }
+        .check_ca(cas)
+        .unwrap_or_default();
+        types::Account {
+            pubkey: info
+                .as_ref()
+                .and_then(|info| types::RsaPublicKey::from_der(info.public_key).ok())
+                .unwrap_or_default(),
+            fingerprint: Self::fingerprint(&old.cert[..]),
+            id: old.id,
+            nonce: old.nonce,
+            data: old.data,
+            created_at: old.created_at,
+            expires_at: info.as_ref().map_or(0, |info| info.not_after),
+            issuer,
+            serial: info.map(|info| info.serial.to_vec()).unwrap_or_default(),
+        }
+    }
+
+    /// Settle what `RawBalance` and the distribution so far add up to into a ledger
+    fn ledger_from_raw_balance(id: types::AccountId) {
+        let created_at = Accounts::get(id).created_at;
+        let term = Self::term_number();
+        let balance = RawBalance::take(id) + Self::votes_cum(term) - Self::votes_cum(created_at);
+        let claimed = if term > created_at { Self::votes_cum(term) } else { 0 };
+        Ledgers::insert(
+            id,
+            types::Ledger {
+                free: balance.max(0),
+                claimed,
+            },
+        );
+    }
+
+    /// Decode the DER public key once and for all
+    fn account_from_v1(old: types::AccountV1) -> types::Account {
+        types::Account {
+            pubkey: types::RsaPublicKey::from_der(&old.pubkey[..]).unwrap_or_default(),
+            fingerprint: old.fingerprint,
+            id: old.id,
+            nonce: old.nonce,
+            data: old.data,
+            created_at: old.created_at,
+            expires_at: old.expires_at,
+            issuer: old.issuer,
+            serial: old.serial,
+        }
+    }
+
+    /// Signatures are only valid on this chain and this runtime version
+    pub fn signing_context() -> types::SigningContext {
+        let genesis_hash = <system::Module<T>>::block_hash(T::BlockNumber::zero());
+        types::SigningContext {
+            genesis_hash: H256::from_slice(genesis_hash.as_ref()),
+            spec_version: T::Version::get().spec_version,
+        }
+    }
+
+    /// The tx nonce must equal the account nonce.
+    /// A lower one is a replay, a higher one can't be applied yet.
+    pub fn check_nonce(account: &types::Account, tbs: &types::Tx) -> DispatchResult {
+        let nonce = tbs.nonce().ok_or(Error::<T>::NoNonce)?;
+        ensure!(nonce >= account.nonce, Error::<T>::StaleNonce);
+        ensure!(nonce == account.nonce, Error::<T>::FutureNonce);
+        Ok(())
+    }
+
+    /// One vote per person per term, of at most `MAX_VOTE_BALANCE_PER_ACCOUNT`
+    pub fn cast_vote(from: types::AccountId, amount: types::Balance) -> DispatchResult {
+        ensure!(Accounts::exists(from), Error::<T>::AccountNotFound);
+        ensure!(amount > 0, Error::<T>::InvalidAmount);
+        ensure!(amount <= MAX_VOTE_BALANCE_PER_ACCOUNT, Error::<T>::VoteTooLarge);
+
+        let term = Self::term_number();
+        ensure!(!Self::has_voted(term, from), Error::<T>::AlreadyVoted);
+
+        let new_bal = CumulativeVotes::get(term)
             .checked_add(amount)
-            .ok_or("Overflow")?;
+            .ok_or(Error::<T>::Overflow)?;
+        ensure!(
+            new_bal - Self::votes_cum_at_term_start(term) <= MAX_VOTE_BALANCE_PER_TERM,
+            Error::<T>::TermVotesExceeded
+        );
+        // every eligible account is paid `amount`
+        let issuance = (EligibleAccounts::get() as types::Balance)
+            .checked_mul(amount)
+            .and_then(|distributed| TotalIssuance::get().checked_add(distributed))
+            .ok_or(Error::<T>::Overflow)?;
+
+        CumulativeVotes::insert(term, new_bal);
+        TotalIssuance::put(issuance);
+        VotesByAccount::insert(term, from, amount);
+        Self::deposit_event(RawEvent::Voted(from, amount));
+        Ok(())
+    }
 
-        let new_rawbal_to = RawBalance::get(to) + amount;
-        let new_rawbal_from = RawBalance::get(from) - amount;
+    pub fn account_of(id: types::AccountId) -> Option<types::Account> {
+        if Accounts::exists(id) {
+            Some(Accounts::get(id))
+        } else {
+            None
+        }
+    }
+
+    pub fn has_voted(term: types::TermNumber, id: types::AccountId) -> bool {
+        Self::vote_of(term, id).is_some()
+    }
+
+    /// `CumulativeVotes` is carried over from the previous term, so this is what `term` started from
+    fn votes_cum_at_term_start(term: types::TermNumber) -> types::Balance {
+        if term == 0 {
+            0
+        } else {
+            Self::votes_cum(term - 1)
+        }
+    }
+
+    /// Credit `to` with freshly minted funds, within the issuer's cap for this term
+    pub fn issue(
+        issuer_id: types::AccountId,
+        to: types::AccountId,
+        amount: types::Balance,
+    ) -> DispatchResult {
+        let issuer = Self::issuer(issuer_id).ok_or(Error::<T>::NotAnIssuer)?;
+        ensure!(amount > 0, Error::<T>::InvalidAmount);
+        let mut recipient = Self::settle(to)?;
+
+        let term = Self::term_number();
+        let minted = MintedInTerm::get(term, issuer_id)
+            .checked_add(amount)
+            .ok_or(Error::<T>::Overflow)?;
+        ensure!(minted <= issuer.cap_per_term, Error::<T>::MintCapExceeded);
+        recipient.free = recipient.free.checked_add(amount).ok_or(Error::<T>::Overflow)?;
+        let issuance = TotalIssuance::get().checked_add(amount).ok_or(Error::<T>::Overflow)?;
 
-        RawBalance::insert(from, new_rawbal_from);
-        RawBalance::insert(to, new_rawbal_to);
-        Self::deposit_event(Event::Transferred(from, to, amount));
+        MintedInTerm::insert(term, issuer_id, minted);
+        TotalIssuance::put(issuance);
+        Ledgers::insert(to, recipient);
+        Self::deposit_event(RawEvent::Minted(issuer_id, to, amount));
         Ok(())
     }
 
     pub fn increment_nonce(id: types::AccountId) -> DispatchResult {
-        ensure!(Accounts::exists(id), "Account not found");
+        ensure!(Accounts::exists(id), Error::<T>::AccountNotFound);
 
         let mut account = Accounts::get(id);
         account.nonce += 1;
@@ -203,13 +1219,85 @@ impl<T: Trait> Module<T> {
 
         Ok(())
     }
-    pub fn compute_balance(id: types::AccountId) -> Result<types::Balance, &'static str> {
-        ensure!(Accounts::exists(id), "Account not found");
+    /// Balance of `id` including accrued distribution
+    pub fn compute_balance(id: types::AccountId) -> Result<types::Balance, DispatchError> {
+        Ok(Self::settle(id)?.free)
+    }
+
+    /// The ledger of `id` with its accrued distribution folded in. Not stored:
+    /// callers store it along with whatever they change.
+    pub fn settle(id: types::AccountId) -> Result<types::Ledger, DispatchError> {
+        ensure!(Accounts::exists(id), Error::<T>::AccountNotFound);
         let created_at = Accounts::get(id).created_at;
-        let raw_bal = RawBalance::get(id);
-        let confirmed_sum = Self::votes_cum(Self::term_number());
-        let distributed_bal = confirmed_sum - Self::votes_cum(created_at);
-        Ok(raw_bal + distributed_bal)
+        let mut ledger = Ledgers::get(id);
+        let term = Self::term_number();
+        // accounts share in the votes of the terms after the one they were created in
+        if term > created_at {
+            let cum = Self::votes_cum(term);
+            let from = ledger.claimed.max(Self::votes_cum(created_at));
+            ledger.free = ledger.free.checked_add(cum - from).ok_or(Error::<T>::Overflow)?;
+            ledger.claimed = cum;
+        }
+        Ok(ledger)
+    }
+
+    /// `amount` on the Balances side
+    fn to_currency(amount: types::Balance) -> Result<BalanceOf<T>, Error<T>> {
+        let amount: u128 = amount.try_into().map_err(|_| Error::<T>::InvalidAmount)?;
+        amount.try_into().map_err(|_| Error::<T>::Overflow)
+    }
+
+    /// `amount` of the Balances pallet on the mynachain side
+    fn from_currency(amount: BalanceOf<T>) -> Result<types::Balance, Error<T>> {
+        let amount: u128 = amount.try_into().map_err(|_| Error::<T>::Overflow)?;
+        amount.try_into().map_err(|_| Error::<T>::Overflow)
+    }
+
+    /// Sum of the balances of all accounts
+    pub fn total_balance() -> types::Balance {
+        (0..AccountCount::get())
+            .filter_map(|i| Self::compute_balance(AccountEnumerator::get(i)).ok())
+            .sum()
+    }
+}
+
+/// Lets other pallets ask whether a caller is a person verified by their card,
+/// without checking certs themselves
+pub trait VerifiedIdentity<AccountId> {
+    /// The mynachain account `who` acts for, if it is a linked key usable right now.
+    /// Only links the key accepted count, never bare offers from a card.
+    /// One person may link several keys; they all map to the same account.
+    fn verified_person(who: &AccountId) -> Option<types::AccountId>;
+
+    fn is_verified_human(who: &AccountId) -> bool {
+        Self::verified_person(who).is_some()
+    }
+}
+
+impl<T: Trait> VerifiedIdentity<T::AccountId> for Module<T> {
+    fn verified_person(who: &T::AccountId) -> Option<types::AccountId> {
+        Self::ensure_linked(who).ok().map(|linked| linked.account)
+    }
+}
+
+/// Origin check for signed calls from a key linked to an active account,
+/// with the key's consent through `accept_key`.
+/// Succeeds with the account, so callers can count each person once.
+pub struct EnsureVerifiedHuman<T>(PhantomData<T>);
+
+impl<T, O> EnsureOrigin<O> for EnsureVerifiedHuman<T>
+where
+    T: Trait,
+    O: Into<Result<system::RawOrigin<T::AccountId>, O>> + From<system::RawOrigin<T::AccountId>>,
+{
+    type Success = types::AccountId;
+
+    fn try_origin(o: O) -> Result<Self::Success, O> {
+        o.into().and_then(|o| match o {
+            system::RawOrigin::Signed(who) => Module::<T>::verified_person(&who)
+                .ok_or_else(|| O::from(system::RawOrigin::Signed(who))),
+            r => Err(O::from(r)),
+        })
     }
 }
 
@@ -218,62 +1306,1800 @@ impl<T: Trait> Module<T> {
 mod tests {
     use super::*;
 
-    use frame_support::{assert_ok, impl_outer_origin, parameter_types, weights::Weight};
-    use sp_core::H256;
-    use sp_runtime::{
-        testing::Header,
-        traits::{BlakeTwo256, IdentityLookup},
-        Perbill,
-    };
-
-    impl_outer_origin! {
-        pub enum Origin for Test {}
-    }
-
-    // For testing the module, we construct most of a mock runtime. This means
-    // first constructing a configuration type (`Test`) which `impl`s each of the
-    // configuration traits of modules we want to use.
-    #[derive(Clone, Eq, PartialEq)]
-    pub struct Test;
-    parameter_types! {
-        pub const BlockHashCount: u64 = 250;
-        pub const MaximumBlockWeight: Weight = 1024;
-        pub const MaximumBlockLength: u32 = 2 * 1024;
-        pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
-    }
-    impl system::Trait for Test {
-        type Origin = Origin;
-        type Call = ();
-        type Index = u64;
-        type BlockNumber = u64;
-        type Hash = H256;
-        type Hashing = BlakeTwo256;
-        type AccountId = u64;
-        type Lookup = IdentityLookup<Self::AccountId>;
-        type Header = Header;
-        type Event = ();
-        type BlockHashCount = BlockHashCount;
-        type MaximumBlockWeight = MaximumBlockWeight;
-        type MaximumBlockLength = MaximumBlockLength;
-        type AvailableBlockRatio = AvailableBlockRatio;
-        type Version = ();
-    }
-    impl Trait for Test {
-        type Event = ();
-    }
-    type MynaChainModule = Module<Test>;
-
-    // This function basically just builds a genesis storage key/value store according to
-    // our desired mockup.
-    fn new_test_ext() -> sp_io::TestExternalities {
-        system::GenesisConfig::default()
-            .build_storage::<Test>()
-            .unwrap()
-            .into()
-    }
+    // the pallet's `Call`, not the one of the mock runtime
+    use super::Call;
+    use crate::mock::{self, *};
+    use frame_support::{assert_err, assert_noop, assert_ok, unsigned::ValidateUnsigned};
+    use parking_lot::RwLock;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use sp_core::offchain::testing::{OffchainState, PendingRequest};
+    use sp_runtime::traits::{OffchainWorker, OnFinalize, OnInitialize};
+    use std::sync::Arc;
 
     #[test]
     fn it_works_for_default_value() {
         new_test_ext().execute_with(|| {});
     }
+
+    /// An account for `cert` that never expires, skipping all cert checks
+    fn dummy_account(cert: Vec<u8>) -> types::Account {
+        types::Account {
+            id: Blake2Hasher::hash(&cert[..]),
+            fingerprint: MynaChainModule::fingerprint(&cert[..]),
+            expires_at: u64::max_value(),
+            ..Default::default()
+        }
+    }
+
+    /// Registers an account whose stored nonce is `nonce`
+    fn account_with_nonce(nonce: types::Nonce) -> types::AccountId {
+        let cert = vec![1, 2, 3, 4];
+        assert_ok!(MynaChainModule::insert_account(dummy_account(cert.clone())));
+        let id = Blake2Hasher::hash(&cert[..]);
+        let mut account = Accounts::get(id);
+        account.nonce = nonce;
+        Accounts::insert(id, account);
+        id
+    }
+
+    fn every_tx(nonce: types::Nonce) -> Vec<types::Tx> {
+        vec![
+            types::Tx::CreateAccount(types::TxCreateAccount {
+                cert: vec![1, 2, 3, 4],
+                nonce,
+            }),
+            types::Tx::Send(types::TxSend {
+                to: H256::zero(),
+                amount: 1,
+                nonce,
+            }),
+            types::Tx::Mint(types::TxMint {
+                to: H256::zero(),
+                amount: 1,
+                nonce,
+            }),
+            types::Tx::Vote(types::TxVote { amount: 1, nonce }),
+            types::Tx::Write(types::TxWrite {
+                data: vec![0xde, 0xad],
+                nonce,
+            }),
+            types::Tx::MigrateAccount(types::TxMigrateAccount {
+                new_cert: vec![5, 6, 7, 8],
+                new_signature: vec![0; 256],
+                nonce,
+            }),
+            types::Tx::Withdraw(types::TxWithdraw {
+                to: 7u64.encode(),
+                amount: 1,
+                nonce,
+            }),
+            types::Tx::LinkKey(types::TxLinkKey {
+                key: 7u64.encode(),
+                scope: types::KeyScope::WriteOnly,
+                expires_at: 0,
+                nonce,
+            }),
+            types::Tx::UnlinkKey(types::TxUnlinkKey {
+                key: 7u64.encode(),
+                nonce,
+            }),
+        ]
+    }
+
+    #[test]
+    fn replayed_txs_are_stale() {
+        new_test_ext().execute_with(|| {
+            let id = account_with_nonce(3);
+            for tbs in every_tx(2) {
+                let tx = types::SignedData {
+                    tbs,
+                    signature: vec![0; 256],
+                    id,
+                };
+                assert_noop!(MynaChainModule::ensure_rsa_signed(&tx), Error::<Test>::StaleNonce);
+            }
+        });
+    }
+
+    #[test]
+    fn future_txs_are_rejected() {
+        new_test_ext().execute_with(|| {
+            let id = account_with_nonce(3);
+            for tbs in every_tx(4) {
+                let tx = types::SignedData {
+                    tbs,
+                    signature: vec![0; 256],
+                    id,
+                };
+                assert_noop!(MynaChainModule::ensure_rsa_signed(&tx), Error::<Test>::FutureNonce);
+            }
+        });
+    }
+
+    #[test]
+    fn current_nonce_is_accepted() {
+        new_test_ext().execute_with(|| {
+            let id = account_with_nonce(3);
+            let account = Accounts::get(id);
+            for tbs in every_tx(3) {
+                assert_ok!(MynaChainModule::check_nonce(&account, &tbs));
+            }
+            assert_noop!(
+                MynaChainModule::check_nonce(&account, &types::Tx::Other),
+                Error::<Test>::NoNonce
+            );
+        });
+    }
+
+    fn validate(tx: types::SignedData) -> TransactionValidity {
+        <MynaChainModule as ValidateUnsigned>::validate_unsigned(&Call::go(tx))
+    }
+
+    #[test]
+    fn go_requires_unsigned_origin() {
+        new_test_ext().execute_with(|| {
+            let tx = types::SignedData::default();
+            assert!(MynaChainModule::go(Origin::signed(1), tx).is_err());
+        });
+    }
+
+    #[test]
+    fn pool_rejects_unknown_and_stale_txs() {
+        new_test_ext().execute_with(|| {
+            assert_eq!(
+                validate(types::SignedData::default()),
+                InvalidTransaction::Call.into()
+            );
+
+            let id = account_with_nonce(3);
+            for tbs in every_tx(2).into_iter().skip(1) {
+                let unknown = types::SignedData {
+                    tbs: tbs.clone(),
+                    signature: vec![0; 256],
+                    id: H256::repeat_byte(0x42),
+                };
+                assert_eq!(validate(unknown), UnknownTransaction::CannotLookup.into());
+
+                let replayed = types::SignedData {
+                    tbs,
+                    signature: vec![0; 256],
+                    id,
+                };
+                assert_eq!(validate(replayed), InvalidTransaction::Stale.into());
+            }
+        });
+    }
+
+    #[test]
+    fn pool_rejects_bad_signatures() {
+        new_test_ext().execute_with(|| {
+            let id = account_with_nonce(3);
+            for tbs in every_tx(3) {
+                let tx = types::SignedData {
+                    tbs,
+                    signature: vec![0; 256],
+                    id,
+                };
+                assert_eq!(validate(tx), InvalidTransaction::BadProof.into());
+            }
+        });
+    }
+
+    #[test]
+    fn signing_payload_is_bound_to_chain_and_version() {
+        new_test_ext().execute_with(|| {
+            let tbs = types::Tx::Write(types::TxWrite {
+                data: vec![1],
+                nonce: 0,
+            });
+            let ctx = MynaChainModule::signing_context();
+            let payload = ctx.payload(&tbs);
+            assert!(payload.starts_with(types::SIGNING_DOMAIN));
+            assert!(payload.ends_with(&tbs.encode()));
+
+            let other_chain = types::SigningContext {
+                genesis_hash: H256::repeat_byte(0xff),
+                ..ctx.clone()
+            };
+            let upgraded = types::SigningContext {
+                spec_version: ctx.spec_version + 1,
+                ..ctx.clone()
+            };
+            assert_ne!(ctx.sighash(&tbs), other_chain.sighash(&tbs));
+            assert_ne!(ctx.sighash(&tbs), upgraded.sighash(&tbs));
+        });
+    }
+
+    #[test]
+    fn issuers_are_managed_by_root() {
+        new_test_ext().execute_with(|| {
+            let id = account_with_nonce(0);
+            assert!(MynaChainModule::add_issuer(Origin::signed(1), id, 100).is_err());
+            assert_ok!(MynaChainModule::add_issuer(Origin::ROOT, id, 100));
+            assert_eq!(
+                MynaChainModule::issuer(id),
+                Some(types::Issuer { cap_per_term: 100 })
+            );
+            assert!(MynaChainModule::remove_issuer(Origin::signed(1), id).is_err());
+            assert_ok!(MynaChainModule::remove_issuer(Origin::ROOT, id));
+            assert_eq!(MynaChainModule::issuer(id), None);
+        });
+    }
+
+    #[test]
+    fn only_issuers_mint_within_cap() {
+        new_test_ext().execute_with(|| {
+            let issuer = account_with_nonce(0);
+            assert_ok!(MynaChainModule::insert_account(dummy_account(vec![9, 9, 9])));
+            let holder = Blake2Hasher::hash(&[9, 9, 9]);
+
+            assert_noop!(MynaChainModule::issue(holder, holder, 10), Error::<Test>::NotAnIssuer);
+
+            assert_ok!(MynaChainModule::add_issuer(Origin::ROOT, issuer, 100));
+            assert_ok!(MynaChainModule::issue(issuer, holder, 60));
+            assert_noop!(
+                MynaChainModule::issue(issuer, holder, 41),
+                Error::<Test>::MintCapExceeded
+            );
+            assert_ok!(MynaChainModule::issue(issuer, holder, 40));
+            assert_eq!(MynaChainModule::ledger(holder).free, 100);
+            assert_eq!(MynaChainModule::ledger(issuer).free, 0);
+            assert_eq!(MynaChainModule::minted_in_term(0, issuer), 100);
+        });
+    }
+
+    fn run_to_block(n: u64) {
+        while System::block_number() < n {
+            let next = System::block_number() + 1;
+            System::set_block_number(next);
+            MynaChainModule::on_initialize(next);
+        }
+    }
+
+    #[test]
+    fn terms_advance_every_term_duration() {
+        new_test_ext().execute_with(|| {
+            CumulativeVotes::insert(0, 50);
+            run_to_block(9);
+            assert_eq!(MynaChainModule::term_number(), 0);
+            run_to_block(10);
+            assert_eq!(MynaChainModule::term_number(), 1);
+            assert_eq!(MynaChainModule::votes_cum(1), 50);
+            run_to_block(25);
+            assert_eq!(MynaChainModule::term_number(), 2);
+        });
+    }
+
+    #[test]
+    fn next_term_is_root_only() {
+        new_test_ext().execute_with(|| {
+            let id = account_with_nonce(0);
+            let tx = types::SignedData {
+                tbs: types::Tx::NextTerm(types::TxNextTerm { nonce: 0 }),
+                signature: vec![0; 256],
+                id,
+            };
+            assert_eq!(validate(tx.clone()), InvalidTransaction::Call.into());
+            assert!(MynaChainModule::go(Origin::NONE, tx).is_err());
+            assert_eq!(MynaChainModule::term_number(), 0);
+
+            assert!(MynaChainModule::force_next_term(Origin::signed(1)).is_err());
+            assert_ok!(MynaChainModule::force_next_term(Origin::ROOT));
+            assert_eq!(MynaChainModule::term_number(), 1);
+        });
+    }
+
+    #[test]
+    fn one_vote_per_person_per_term() {
+        new_test_ext().execute_with(|| {
+            let id = account_with_nonce(0);
+            assert_eq!(MynaChainModule::vote_of(0, id), None);
+            assert_noop!(
+                MynaChainModule::cast_vote(id, MAX_VOTE_BALANCE_PER_ACCOUNT + 1),
+                Error::<Test>::VoteTooLarge
+            );
+            assert_ok!(MynaChainModule::cast_vote(id, 30));
+            assert!(MynaChainModule::has_voted(0, id));
+            assert_eq!(MynaChainModule::vote_of(0, id), Some(30));
+            assert_noop!(MynaChainModule::cast_vote(id, 1), Error::<Test>::AlreadyVoted);
+            assert_eq!(MynaChainModule::votes_cum(0), 30);
+
+            assert_ok!(MynaChainModule::force_next_term(Origin::ROOT));
+            assert!(!MynaChainModule::has_voted(1, id));
+            assert_ok!(MynaChainModule::cast_vote(id, 20));
+            assert_eq!(MynaChainModule::vote_of(1, id), Some(20));
+            assert_eq!(MynaChainModule::votes_cum(1), 50);
+        });
+    }
+
+    #[test]
+    fn term_vote_limit_applies_per_term() {
+        new_test_ext().execute_with(|| {
+            let id = account_with_nonce(0);
+            CumulativeVotes::insert(0, MAX_VOTE_BALANCE_PER_TERM);
+            assert_noop!(MynaChainModule::cast_vote(id, 1), Error::<Test>::TermVotesExceeded);
+
+            assert_ok!(MynaChainModule::force_next_term(Origin::ROOT));
+            assert_ok!(MynaChainModule::cast_vote(id, 1));
+            assert_eq!(
+                MynaChainModule::votes_cum(1),
+                MAX_VOTE_BALANCE_PER_TERM + 1
+            );
+        });
+    }
+
+    #[test]
+    fn create_account_cannot_be_replayed() {
+        new_test_ext().execute_with(|| {
+            let cert = vec![5, 6, 7, 8];
+            assert_ok!(MynaChainModule::insert_account(dummy_account(cert.clone())));
+            assert_noop!(
+                MynaChainModule::insert_account(dummy_account(cert)),
+                Error::<Test>::AccountAlreadyExists
+            );
+        });
+    }
+
+    /// Register `card` through the same path as a real tx
+    fn register(card: &Card) -> types::AccountId {
+        assert_ok!(MynaChainModule::go(Origin::NONE, card.create_account()));
+        card.id()
+    }
+
+    /// Make `card` an issuer and mint `amount` to it
+    fn fund(card: &Card, amount: types::Balance) {
+        assert_ok!(MynaChainModule::add_issuer(Origin::ROOT, card.id(), amount));
+        let nonce = MynaChainModule::account(card.id()).nonce;
+        let tx = card.sign(types::Tx::Mint(types::TxMint {
+            to: card.id(),
+            amount,
+            nonce,
+        }));
+        assert_ok!(MynaChainModule::go(Origin::NONE, tx));
+    }
+
+    fn send(card: &Card, to: types::AccountId, amount: types::Balance) -> types::SignedData {
+        let nonce = MynaChainModule::account(card.id()).nonce;
+        card.sign(types::Tx::Send(types::TxSend { to, amount, nonce }))
+    }
+
+    #[test]
+    fn card_creates_account() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let id = register(&alice);
+            let account = MynaChainModule::account(id);
+            assert_eq!(account.fingerprint, MynaChainModule::fingerprint(&alice.cert));
+            assert_eq!(account.pubkey, alice.public_key());
+            assert_eq!(account.nonce, 0);
+            assert_eq!(MynaChainModule::account_count(), 1);
+            assert_eq!(MynaChainModule::account_enum(0), id);
+        });
+    }
+
+    #[test]
+    fn create_account_needs_trusted_ca() {
+        new_test_ext().execute_with(|| {
+            let mallory = rogue_card(0);
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, mallory.create_account()),
+                Error::<Test>::UnknownCa
+            );
+            assert_eq!(
+                validate(mallory.create_account()),
+                InvalidTransaction::BadProof.into()
+            );
+
+            // a forged signature is turned down before any CA is checked
+            let mut forged = mallory.create_account();
+            forged.signature = vec![0; 128];
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, forged),
+                Error::<Test>::BadSignature
+            );
+        });
+    }
+
+    #[test]
+    fn create_account_needs_signature_of_cert_holder() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card(1);
+            let mut tx = alice.create_account();
+            tx.signature = bob.sign(tx.tbs.clone()).signature;
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, tx),
+                Error::<Test>::BadSignature
+            );
+        });
+    }
+
+    #[test]
+    fn genesis_trusts_test_ca() {
+        new_test_ext().execute_with(|| {
+            let id = Blake2Hasher::hash(&TEST_CA.cert[..]);
+            assert_eq!(MynaChainModule::trusted_ca(id).cert, TEST_CA.cert);
+            assert_eq!(MynaChainModule::active_cas(), vec![TEST_CA.cert.clone()]);
+        });
+    }
+
+    #[test]
+    fn cas_are_managed_by_root() {
+        new_test_ext().execute_with(|| {
+            let rogue_ca = Blake2Hasher::hash(&ROGUE_CA.cert[..]);
+            assert!(MynaChainModule::add_ca(Origin::signed(1), ROGUE_CA.cert.clone(), 0).is_err());
+            assert!(MynaChainModule::retire_ca(Origin::signed(1), rogue_ca, 0).is_err());
+
+            assert_ok!(MynaChainModule::add_ca(Origin::ROOT, ROGUE_CA.cert.clone(), 0));
+            assert_noop!(
+                MynaChainModule::add_ca(Origin::ROOT, ROGUE_CA.cert.clone(), 0),
+                Error::<Test>::CaAlreadyExists
+            );
+            register(&rogue_card(0));
+        });
+    }
+
+    #[test]
+    fn active_cas_are_capped() {
+        new_test_ext().execute_with(|| {
+            let extra = TestCa::new(key(5), "Extra CA");
+            let spare = TestCa::new(key(6), "Spare CA");
+            assert_ok!(MynaChainModule::add_ca(Origin::ROOT, ROGUE_CA.cert.clone(), 0));
+            assert_ok!(MynaChainModule::add_ca(Origin::ROOT, EXPIRED_CA.cert.clone(), 0));
+            // scheduled CAs take a slot too
+            assert_ok!(MynaChainModule::add_ca(Origin::ROOT, extra.cert.clone(), 10));
+            assert_noop!(
+                MynaChainModule::add_ca(Origin::ROOT, spare.cert.clone(), 0),
+                Error::<Test>::TooManyCas
+            );
+
+            let test_ca = Blake2Hasher::hash(&TEST_CA.cert[..]);
+            assert_ok!(MynaChainModule::retire_ca(Origin::ROOT, test_ca, 3));
+            System::set_block_number(3);
+            assert_ok!(MynaChainModule::add_ca(Origin::ROOT, spare.cert.clone(), 3));
+
+            // a retired CA can't be brought back past the cap
+            assert_noop!(
+                MynaChainModule::retire_ca(Origin::ROOT, test_ca, 100),
+                Error::<Test>::CaAlreadyRetired
+            );
+            assert_noop!(
+                MynaChainModule::retire_ca(Origin::ROOT, test_ca, 3),
+                Error::<Test>::CaAlreadyRetired
+            );
+            assert_ok!(MynaChainModule::retire_ca(Origin::ROOT, test_ca, 2));
+        });
+    }
+
+    #[test]
+    fn retired_cas_can_be_removed() {
+        new_test_ext().execute_with(|| {
+            let test_ca = Blake2Hasher::hash(&TEST_CA.cert[..]);
+            assert_noop!(
+                MynaChainModule::remove_ca(Origin::ROOT, test_ca),
+                Error::<Test>::CaNotRetired
+            );
+            assert_ok!(MynaChainModule::retire_ca(Origin::ROOT, test_ca, 3));
+            assert_noop!(
+                MynaChainModule::remove_ca(Origin::ROOT, test_ca),
+                Error::<Test>::CaNotRetired
+            );
+            assert!(MynaChainModule::remove_ca(Origin::signed(1), test_ca).is_err());
+
+            System::set_block_number(3);
+            assert_ok!(MynaChainModule::remove_ca(Origin::ROOT, test_ca));
+            assert!(!<TrustedCas<Test>>::exists(test_ca));
+            assert_noop!(
+                MynaChainModule::update_crl(Origin::ROOT, TEST_CA.crl(&[1], "210101000000Z")),
+                Error::<Test>::UntrustedCrl
+            );
+            assert_noop!(
+                MynaChainModule::remove_ca(Origin::ROOT, test_ca),
+                Error::<Test>::CaNotFound
+            );
+        });
+    }
+
+    #[test]
+    fn scheduled_ca_is_trusted_from_its_block() {
+        new_test_ext().execute_with(|| {
+            assert_ok!(MynaChainModule::add_ca(Origin::ROOT, ROGUE_CA.cert.clone(), 5));
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, rogue_card(0).create_account()),
+                Error::<Test>::UnknownCa
+            );
+            System::set_block_number(5);
+            register(&rogue_card(0));
+        });
+    }
+
+    #[test]
+    fn retired_ca_vouches_for_no_new_accounts() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            register(&alice);
+            let test_ca = Blake2Hasher::hash(&TEST_CA.cert[..]);
+            assert_ok!(MynaChainModule::retire_ca(Origin::ROOT, test_ca, 3));
+
+            System::set_block_number(3);
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, card(1).create_account()),
+                Error::<Test>::UnknownCa
+            );
+            let tx = alice.sign(types::Tx::Write(types::TxWrite {
+                data: vec![1],
+                nonce: 0,
+            }));
+            assert_ok!(MynaChainModule::go(Origin::NONE, tx));
+        });
+    }
+
+    #[test]
+    fn expired_or_early_certs_cannot_create_accounts() {
+        new_test_ext().execute_with(|| {
+            let expired = card_with_validity(0, ("150101000000Z", "201231235959Z"));
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, expired.create_account()),
+                Error::<Test>::CertificateExpired
+            );
+            let early = card_with_validity(1, ("300101000000Z", "351231235959Z"));
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, early.create_account()),
+                Error::<Test>::CertificateNotYetValid
+            );
+            assert_eq!(
+                validate(early.create_account()),
+                InvalidTransaction::BadProof.into()
+            );
+        });
+    }
+
+    #[test]
+    fn expired_ca_vouches_for_no_accounts() {
+        new_test_ext().execute_with(|| {
+            assert_ok!(MynaChainModule::add_ca(Origin::ROOT, EXPIRED_CA.cert.clone(), 0));
+            assert!(!MynaChainModule::active_cas().contains(&EXPIRED_CA.cert));
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, card_of(&EXPIRED_CA, 0).create_account()),
+                Error::<Test>::UnknownCa
+            );
+        });
+    }
+
+    #[test]
+    fn account_expires_with_its_cert() {
+        new_test_ext().execute_with(|| {
+            // valid until 2021-06-30, the mock starts at 2021-01-01
+            let alice = card_with_validity(0, ("200101000000Z", "210630235959Z"));
+            register(&alice);
+            assert_eq!(
+                MynaChainModule::account_status(alice.id()),
+                Some(types::AccountStatus::Active)
+            );
+
+            Timestamp::set_timestamp(1_625_097_600_000); // 2021-07-01
+            assert_eq!(
+                MynaChainModule::account_status(alice.id()),
+                Some(types::AccountStatus::AccountExpired)
+            );
+            let tx = alice.sign(types::Tx::Write(types::TxWrite {
+                data: vec![1],
+                nonce: 0,
+            }));
+            assert_noop!(MynaChainModule::go(Origin::NONE, tx.clone()), Error::<Test>::AccountExpired);
+            assert_eq!(
+                validate(tx),
+                InvalidTransaction::Custom(ACCOUNT_EXPIRED).into()
+            );
+        });
+    }
+
+    #[test]
+    fn create_account_twice_fails() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            register(&alice);
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, alice.create_account()),
+                Error::<Test>::AccountAlreadyExists
+            );
+            assert_eq!(
+                validate(alice.create_account()),
+                InvalidTransaction::Stale.into()
+            );
+        });
+    }
+
+    #[test]
+    fn send_moves_funds() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card(1);
+            register(&alice);
+            register(&bob);
+            fund(&alice, 100);
+
+            assert_ok!(MynaChainModule::go(Origin::NONE, send(&alice, bob.id(), 30)));
+            assert_eq!(MynaChainModule::compute_balance(alice.id()), Ok(70));
+            assert_eq!(MynaChainModule::compute_balance(bob.id()), Ok(30));
+            assert_eq!(MynaChainModule::account(alice.id()).nonce, 2);
+        });
+    }
+
+    #[test]
+    fn send_cannot_overdraw() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card(1);
+            register(&alice);
+            register(&bob);
+            fund(&alice, 10);
+            assert_err!(
+                MynaChainModule::go(Origin::NONE, send(&alice, bob.id(), 11)),
+                Error::<Test>::InsufficientBalance
+            );
+            assert_eq!(MynaChainModule::compute_balance(alice.id()), Ok(10));
+            assert_eq!(MynaChainModule::compute_balance(bob.id()), Ok(0));
+        });
+    }
+
+    #[test]
+    fn failed_send_cannot_be_applied_later() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card(1);
+            register(&alice);
+            register(&bob);
+            fund(&bob, 100);
+
+            let tx = send(&alice, bob.id(), 30);
+            assert_err!(MynaChainModule::go(Origin::NONE, tx.clone()), Error::<Test>::InsufficientBalance);
+            assert_eq!(MynaChainModule::account(alice.id()).nonce, 1);
+
+            // alice can now afford it, but the failed tx spent its nonce
+            assert_ok!(MynaChainModule::go(Origin::NONE, send(&bob, alice.id(), 50)));
+            assert_noop!(MynaChainModule::go(Origin::NONE, tx.clone()), Error::<Test>::StaleNonce);
+            assert_eq!(validate(tx), InvalidTransaction::Stale.into());
+            assert_eq!(MynaChainModule::compute_balance(alice.id()), Ok(50));
+        });
+    }
+
+    #[test]
+    fn send_cannot_be_replayed() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card(1);
+            register(&alice);
+            register(&bob);
+            fund(&alice, 100);
+
+            let tx = send(&alice, bob.id(), 30);
+            assert_ok!(MynaChainModule::go(Origin::NONE, tx.clone()));
+            assert_noop!(MynaChainModule::go(Origin::NONE, tx.clone()), Error::<Test>::StaleNonce);
+            assert_eq!(validate(tx), InvalidTransaction::Stale.into());
+            assert_eq!(MynaChainModule::compute_balance(bob.id()), Ok(30));
+        });
+    }
+
+    #[test]
+    fn only_the_card_holder_can_send() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card(1);
+            register(&alice);
+            register(&bob);
+            fund(&alice, 100);
+
+            let mut tx = send(&alice, bob.id(), 30);
+            tx.signature = bob.sign(tx.tbs.clone()).signature;
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, tx.clone()),
+                Error::<Test>::BadSignature
+            );
+            assert_eq!(validate(tx), InvalidTransaction::BadProof.into());
+        });
+    }
+
+    #[test]
+    fn mint_needs_issuer() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            register(&alice);
+            let tx = alice.sign(types::Tx::Mint(types::TxMint {
+                to: alice.id(),
+                amount: 10,
+                nonce: 0,
+            }));
+            assert_err!(MynaChainModule::go(Origin::NONE, tx), Error::<Test>::NotAnIssuer);
+
+            fund(&alice, 10);
+            assert_eq!(MynaChainModule::compute_balance(alice.id()), Ok(10));
+        });
+    }
+
+    #[test]
+    fn vote_is_distributed_to_existing_accounts() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card(1);
+            register(&alice);
+            register(&bob);
+            // accounts share in the votes of the terms after the one they joined in
+            assert_ok!(MynaChainModule::force_next_term(Origin::ROOT));
+
+            let tx = alice.sign(types::Tx::Vote(types::TxVote {
+                amount: 50,
+                nonce: 0,
+            }));
+            assert_ok!(MynaChainModule::go(Origin::NONE, tx));
+            assert_eq!(MynaChainModule::vote_of(1, alice.id()), Some(50));
+            assert_eq!(MynaChainModule::compute_balance(bob.id()), Ok(50));
+        });
+    }
+
+    #[test]
+    fn accrued_distribution_is_spent_once() {
+        new_test_ext().execute_with(|| {
+            let alice = account_in_term(1);
+            let bob = account_in_term(2);
+            assert_ok!(MynaChainModule::force_next_term(Origin::ROOT));
+            assert_ok!(MynaChainModule::cast_vote(bob, 40));
+
+            assert_ok!(MynaChainModule::transfer(alice, bob, 30));
+            assert_eq!(MynaChainModule::ledger(alice), types::Ledger { free: 10, claimed: 40 });
+            assert_noop!(
+                MynaChainModule::transfer(alice, bob, 11),
+                Error::<Test>::InsufficientBalance
+            );
+            // votes later in the term still reach accounts that already settled
+            assert_ok!(MynaChainModule::cast_vote(alice, 5));
+            assert_eq!(MynaChainModule::compute_balance(alice), Ok(15));
+            assert_eq!(MynaChainModule::compute_balance(bob), Ok(75));
+        });
+    }
+
+    #[test]
+    fn transfers_must_be_positive() {
+        new_test_ext().execute_with(|| {
+            let alice = account_in_term(1);
+            let bob = account_in_term(2);
+            assert_noop!(MynaChainModule::transfer(alice, bob, -10), Error::<Test>::InvalidAmount);
+            assert_noop!(MynaChainModule::transfer(alice, bob, 0), Error::<Test>::InvalidAmount);
+        });
+    }
+
+    /// Registers an account created in the current term
+    fn account_in_term(n: u8) -> types::AccountId {
+        let account = types::Account {
+            created_at: MynaChainModule::term_number(),
+            ..dummy_account(vec![n; 4])
+        };
+        let id = account.id;
+        assert_ok!(MynaChainModule::insert_account(account));
+        id
+    }
+
+    /// Random account creation, minting, sending, voting and term changes, against a
+    /// model where each vote pays every account from an earlier term in full
+    fn random_ledger_ops(seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let issuer = account_in_term(0);
+        assert_ok!(MynaChainModule::add_issuer(Origin::ROOT, issuer, types::Balance::max_value()));
+        // (id, term it was created in, balance)
+        let mut model = vec![(issuer, 0, 0)];
+        let mut issuance: types::Balance = 0;
+
+        for step in 0..200 {
+            let term = MynaChainModule::term_number();
+            let pick = rng.gen_range(0, model.len());
+            let other = rng.gen_range(0, model.len());
+            match rng.gen_range(0, 10) {
+                0 if model.len() < 250 => {
+                    model.push((account_in_term(model.len() as u8), term, 0));
+                }
+                1 | 2 => {
+                    let amount = rng.gen_range(1, 100);
+                    assert_ok!(MynaChainModule::issue(issuer, model[pick].0, amount));
+                    model[pick].2 += amount;
+                    issuance += amount;
+                }
+                3 | 4 | 5 => {
+                    let amount = rng.gen_range(1, model[pick].2 + 20);
+                    let res = MynaChainModule::transfer(model[pick].0, model[other].0, amount);
+                    if amount <= model[pick].2 {
+                        assert_ok!(res);
+                        model[pick].2 -= amount;
+                        model[other].2 += amount;
+                    } else {
+                        assert_eq!(res, Err(Error::<Test>::InsufficientBalance.into()));
+                    }
+                }
+                6 | 7 | 8 => {
+                    let amount = rng.gen_range(1, MAX_VOTE_BALANCE_PER_ACCOUNT + 1);
+                    let voted = MynaChainModule::has_voted(term, model[pick].0);
+                    let res = MynaChainModule::cast_vote(model[pick].0, amount);
+                    if voted {
+                        assert_eq!(res, Err(Error::<Test>::AlreadyVoted.into()));
+                    } else {
+                        assert_ok!(res);
+                        for account in model.iter_mut().filter(|a| a.1 < term) {
+                            account.2 += amount;
+                            issuance += amount;
+                        }
+                    }
+                }
+                _ => MynaChainModule::advance_term(step),
+            }
+
+            for (id, _, balance) in model.iter() {
+                assert_eq!(MynaChainModule::compute_balance(*id), Ok(*balance));
+                assert!(MynaChainModule::ledger(*id).free >= 0);
+            }
+            assert_eq!(MynaChainModule::total_balance(), issuance);
+            assert_eq!(MynaChainModule::total_issuance(), issuance);
+            MynaChainModule::on_finalize(step);
+        }
+    }
+
+    #[test]
+    fn issuance_tracks_mints_and_distribution() {
+        new_test_ext().execute_with(|| {
+            let alice = account_in_term(1);
+            let bob = account_in_term(2);
+            assert_ok!(MynaChainModule::add_issuer(Origin::ROOT, alice, 100));
+            assert_ok!(MynaChainModule::issue(alice, bob, 30));
+            assert_eq!(MynaChainModule::total_issuance(), 30);
+
+            // no account is eligible in the genesis term yet
+            assert_ok!(MynaChainModule::cast_vote(alice, 10));
+            assert_eq!(MynaChainModule::total_issuance(), 30);
+
+            assert_ok!(MynaChainModule::force_next_term(Origin::ROOT));
+            account_in_term(3);
+            assert_eq!(MynaChainModule::eligible_accounts(), 2);
+            assert_ok!(MynaChainModule::cast_vote(alice, 10));
+            assert_eq!(MynaChainModule::total_issuance(), 50);
+
+            assert_ok!(MynaChainModule::transfer(bob, alice, 25));
+            assert_eq!(MynaChainModule::total_issuance(), 50);
+            assert_eq!(MynaChainModule::total_balance(), 50);
+            MynaChainModule::on_finalize(1);
+        });
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "TotalIssuance is off from the sum of balances")]
+    fn finalize_catches_unaccounted_funds() {
+        new_test_ext().execute_with(|| {
+            let alice = account_in_term(1);
+            Ledgers::insert(alice, types::Ledger { free: 10, claimed: 0 });
+            MynaChainModule::on_finalize(1);
+        });
+    }
+
+    #[test]
+    fn ledger_matches_model_under_random_ops() {
+        for seed in 0..20 {
+            new_test_ext().execute_with(|| random_ledger_ops(seed));
+        }
+    }
+
+    fn bridge_to(card: &Card, to: u64, amount: types::Balance) -> types::SignedData {
+        let nonce = MynaChainModule::account(card.id()).nonce;
+        card.sign(types::Tx::Withdraw(types::TxWithdraw {
+            to: to.encode(),
+            amount,
+            nonce,
+        }))
+    }
+
+    /// What both sides of the bridge hold together
+    fn bridged_supply() -> u128 {
+        Balances::total_issuance() as u128 + MynaChainModule::total_issuance() as u128
+    }
+
+    #[test]
+    fn deposit_and_withdraw_conserve_value() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            register(&alice);
+            let _ = Balances::deposit_creating(&7, 100);
+
+            assert_ok!(MynaChainModule::deposit(Origin::signed(7), alice.id(), 60));
+            assert_eq!(Balances::free_balance(7u64), 40);
+            assert_eq!(MynaChainModule::compute_balance(alice.id()), Ok(60));
+            assert_eq!(bridged_supply(), 100);
+
+            assert_ok!(MynaChainModule::go(Origin::NONE, bridge_to(&alice, 8, 25)));
+            assert_eq!(Balances::free_balance(8u64), 25);
+            assert_eq!(MynaChainModule::compute_balance(alice.id()), Ok(35));
+            assert_eq!(MynaChainModule::total_issuance(), 35);
+            assert_eq!(bridged_supply(), 100);
+            MynaChainModule::on_finalize(1);
+        });
+    }
+
+    #[test]
+    fn bridge_cannot_overdraw_either_side() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            register(&alice);
+            let _ = Balances::deposit_creating(&7, 100);
+
+            assert!(MynaChainModule::deposit(Origin::signed(7), alice.id(), 101).is_err());
+            assert_noop!(
+                MynaChainModule::deposit(Origin::signed(7), card(1).id(), 10),
+                Error::<Test>::AccountNotFound
+            );
+            assert_err!(
+                MynaChainModule::go(Origin::NONE, bridge_to(&alice, 8, 1)),
+                Error::<Test>::InsufficientBalance
+            );
+
+            assert_ok!(MynaChainModule::deposit(Origin::signed(7), alice.id(), 10));
+            let mut tx = bridge_to(&alice, 8, 5);
+            if let types::Tx::Withdraw(t) = &mut tx.tbs {
+                t.to = vec![8];
+            }
+            let tx = alice.sign(tx.tbs);
+            assert_err!(MynaChainModule::go(Origin::NONE, tx), Error::<Test>::InvalidDestination);
+            assert_err!(
+                MynaChainModule::go(Origin::NONE, bridge_to(&alice, 8, -5)),
+                Error::<Test>::InvalidAmount
+            );
+            assert_eq!(bridged_supply(), 100);
+        });
+    }
+
+    #[test]
+    fn deposits_to_unusable_accounts_are_refused() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card_with_validity(1, ("200101000000Z", "210630235959Z"));
+            register(&alice);
+            register(&bob);
+            let _ = Balances::deposit_creating(&7, 100);
+
+            assert_ok!(MynaChainModule::update_crl(Origin::ROOT, TEST_CA.crl(&[1], "210101000000Z")));
+            assert_noop!(
+                MynaChainModule::deposit(Origin::signed(7), alice.id(), 10),
+                Error::<Test>::AccountRevoked
+            );
+            Timestamp::set_timestamp(1_625_097_600_000); // 2021-07-01
+            assert_noop!(
+                MynaChainModule::deposit(Origin::signed(7), bob.id(), 10),
+                Error::<Test>::AccountExpired
+            );
+            assert_eq!(Balances::free_balance(7u64), 100);
+        });
+    }
+
+    #[test]
+    fn write_stores_data() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            register(&alice);
+            let tx = alice.sign(types::Tx::Write(types::TxWrite {
+                data: b"hello".to_vec(),
+                nonce: 0,
+            }));
+            assert_ok!(MynaChainModule::go(Origin::NONE, tx));
+            assert_eq!(MynaChainModule::account(alice.id()).data, b"hello".to_vec());
+        });
+    }
+
+    #[test]
+    fn writes_are_bounded() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            register(&alice);
+            let tx = alice.sign(types::Tx::Write(types::TxWrite {
+                data: vec![0; MAX_DATA_LEN + 1],
+                nonce: 0,
+            }));
+            assert_eq!(validate(tx.clone()), InvalidTransaction::ExhaustsResources.into());
+            assert_err!(MynaChainModule::go(Origin::NONE, tx), Error::<Test>::DataTooLarge);
+            assert_eq!(MynaChainModule::account(alice.id()).data, Vec::<u8>::new());
+        });
+    }
+
+    fn offer(card: &Card, key: u64, scope: types::KeyScope, expires_at: types::TermNumber) -> DispatchResult {
+        let nonce = MynaChainModule::account(card.id()).nonce;
+        let tx = card.sign(types::Tx::LinkKey(types::TxLinkKey {
+            key: key.encode(),
+            scope,
+            expires_at,
+            nonce,
+        }));
+        MynaChainModule::go(Origin::NONE, tx)
+    }
+
+    fn link(card: &Card, key: u64, scope: types::KeyScope, expires_at: types::TermNumber) -> DispatchResult {
+        offer(card, key, scope, expires_at)?;
+        MynaChainModule::accept_key(Origin::signed(key), card.id())
+    }
+
+    #[test]
+    fn keys_must_accept_their_link() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card(1);
+            register(&alice);
+            register(&bob);
+
+            assert_ok!(offer(&alice, 7, types::KeyScope::WriteOnly, 5));
+            assert_noop!(
+                MynaChainModule::linked_write(Origin::signed(7), vec![]),
+                Error::<Test>::KeyNotLinked
+            );
+            assert_noop!(
+                MynaChainModule::accept_key(Origin::signed(8), alice.id()),
+                Error::<Test>::KeyNotOffered
+            );
+            assert_noop!(
+                MynaChainModule::accept_key(Origin::signed(7), bob.id()),
+                Error::<Test>::KeyNotOffered
+            );
+
+            // an offer from another card doesn't replace the one the key wants
+            assert_ok!(offer(&bob, 7, types::KeyScope::WriteOnly, 5));
+            assert_ok!(MynaChainModule::accept_key(Origin::signed(7), alice.id()));
+            assert_eq!(MynaChainModule::linked_key(7).map(|linked| linked.account), Some(alice.id()));
+            assert_noop!(
+                MynaChainModule::accept_key(Origin::signed(7), bob.id()),
+                Error::<Test>::KeyInUse
+            );
+
+            // offers expire too
+            assert_ok!(offer(&alice, 8, types::KeyScope::WriteOnly, 0));
+            MynaChainModule::advance_term(1);
+            assert_noop!(
+                MynaChainModule::accept_key(Origin::signed(8), alice.id()),
+                Error::<Test>::KeyExpired
+            );
+        });
+    }
+
+    #[test]
+    fn linked_key_sends_within_its_limit() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card(1);
+            register(&alice);
+            register(&bob);
+            fund(&alice, 100);
+
+            assert_ok!(link(&alice, 7, types::KeyScope::Spend { limit: 30 }, 5));
+            assert_ok!(MynaChainModule::linked_send(Origin::signed(7), bob.id(), 20));
+            assert_ok!(MynaChainModule::linked_write(Origin::signed(7), b"hi".to_vec()));
+            assert_noop!(
+                MynaChainModule::linked_send(Origin::signed(7), bob.id(), 11),
+                Error::<Test>::SendLimitExceeded
+            );
+            assert_noop!(
+                MynaChainModule::linked_send(Origin::signed(8), bob.id(), 1),
+                Error::<Test>::KeyNotLinked
+            );
+            assert_eq!(MynaChainModule::compute_balance(bob.id()), Ok(20));
+            assert_eq!(MynaChainModule::account(alice.id()).data, b"hi".to_vec());
+
+            // relinking resets the limit
+            assert_ok!(link(&alice, 7, types::KeyScope::Spend { limit: 30 }, 5));
+            assert_ok!(MynaChainModule::linked_send(Origin::signed(7), bob.id(), 30));
+        });
+    }
+
+    #[test]
+    fn write_only_key_cannot_send() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            register(&alice);
+            fund(&alice, 100);
+
+            assert_ok!(link(&alice, 7, types::KeyScope::WriteOnly, 5));
+            assert_noop!(
+                MynaChainModule::linked_send(Origin::signed(7), alice.id(), 1),
+                Error::<Test>::KeyNotPermitted
+            );
+            assert_ok!(MynaChainModule::linked_write(Origin::signed(7), b"hi".to_vec()));
+        });
+    }
+
+    #[test]
+    fn keys_expire_and_can_be_unlinked() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card(1);
+            register(&alice);
+            register(&bob);
+
+            assert_ok!(link(&alice, 7, types::KeyScope::WriteOnly, 0));
+            assert_ok!(link(&alice, 8, types::KeyScope::WriteOnly, 5));
+            assert_err!(link(&bob, 8, types::KeyScope::WriteOnly, 5), Error::<Test>::KeyInUse);
+
+            MynaChainModule::advance_term(1);
+            assert_noop!(
+                MynaChainModule::linked_write(Origin::signed(7), vec![]),
+                Error::<Test>::KeyExpired
+            );
+            assert_err!(link(&alice, 9, types::KeyScope::WriteOnly, 0), Error::<Test>::KeyExpired);
+
+            let unlink = |card: &Card| {
+                card.sign(types::Tx::UnlinkKey(types::TxUnlinkKey {
+                    key: 8u64.encode(),
+                    nonce: MynaChainModule::account(card.id()).nonce,
+                }))
+            };
+            assert_err!(MynaChainModule::go(Origin::NONE, unlink(&bob)), Error::<Test>::KeyNotLinked);
+            assert_ok!(MynaChainModule::go(Origin::NONE, unlink(&alice)));
+            assert_noop!(
+                MynaChainModule::linked_write(Origin::signed(8), vec![]),
+                Error::<Test>::KeyNotLinked
+            );
+            assert_ok!(link(&bob, 8, types::KeyScope::WriteOnly, 5));
+        });
+    }
+
+    #[test]
+    fn keys_of_revoked_accounts_stop_working() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card(1);
+            register(&alice);
+            register(&bob);
+            assert_ok!(link(&alice, 7, types::KeyScope::WriteOnly, 5));
+            assert_ok!(link(&alice, 8, types::KeyScope::WriteOnly, 5));
+
+            assert_ok!(MynaChainModule::update_crl(Origin::ROOT, TEST_CA.crl(&[1], "210101000000Z")));
+            assert_noop!(
+                MynaChainModule::linked_write(Origin::signed(7), vec![]),
+                Error::<Test>::AccountRevoked
+            );
+
+            // the revoked card can't unlink its keys any more, but they aren't stuck
+            assert_ok!(link(&bob, 7, types::KeyScope::WriteOnly, 5));
+            assert_ok!(MynaChainModule::linked_write(Origin::signed(7), b"hi".to_vec()));
+            assert_eq!(MynaChainModule::account(bob.id()).data, b"hi".to_vec());
+            assert_ok!(MynaChainModule::release_key(Origin::signed(8)));
+            assert_eq!(MynaChainModule::linked_key(8), None);
+            assert_noop!(MynaChainModule::release_key(Origin::signed(8)), Error::<Test>::KeyNotLinked);
+        });
+    }
+
+    #[test]
+    fn unlinking_a_reoffered_key_drops_the_link() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            register(&alice);
+            assert_ok!(link(&alice, 7, types::KeyScope::WriteOnly, 5));
+            assert_ok!(offer(&alice, 7, types::KeyScope::Spend { limit: 10 }, 5));
+
+            let tx = alice.sign(types::Tx::UnlinkKey(types::TxUnlinkKey {
+                key: 7u64.encode(),
+                nonce: MynaChainModule::account(alice.id()).nonce,
+            }));
+            assert_ok!(MynaChainModule::go(Origin::NONE, tx));
+            assert_noop!(
+                MynaChainModule::linked_write(Origin::signed(7), vec![]),
+                Error::<Test>::KeyNotLinked
+            );
+            assert_noop!(
+                MynaChainModule::accept_key(Origin::signed(7), alice.id()),
+                Error::<Test>::KeyNotOffered
+            );
+        });
+    }
+
+    #[test]
+    fn linked_keys_are_verified_humans() {
+        new_test_ext().execute_with(|| {
+            type Human = EnsureVerifiedHuman<Test>;
+            let alice = card(0);
+            register(&alice);
+            assert_ok!(link(&alice, 7, types::KeyScope::WriteOnly, 0));
+            assert_ok!(link(&alice, 8, types::KeyScope::Spend { limit: 1 }, 5));
+
+            assert_eq!(Human::try_origin(Origin::signed(7)).ok(), Some(alice.id()));
+            assert_eq!(Human::try_origin(Origin::signed(8)).ok(), Some(alice.id()));
+            assert!(Human::try_origin(Origin::signed(9)).is_err());
+            assert_ok!(offer(&alice, 9, types::KeyScope::WriteOnly, 5));
+            assert!(Human::try_origin(Origin::signed(9)).is_err());
+            assert_eq!(MynaChainModule::verified_person(&9), None);
+            assert!(Human::try_origin(Origin::ROOT).is_err());
+            assert!(Human::try_origin(Origin::NONE).is_err());
+
+            MynaChainModule::advance_term(1);
+            assert!(!MynaChainModule::is_verified_human(&7));
+            assert!(MynaChainModule::is_verified_human(&8));
+
+            assert_ok!(MynaChainModule::update_crl(Origin::ROOT, TEST_CA.crl(&[1], "210101000000Z")));
+            assert_eq!(MynaChainModule::verified_person(&8), None);
+        });
+    }
+
+    #[test]
+    fn signed_next_term_is_rejected() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            register(&alice);
+            let tx = alice.sign(types::Tx::NextTerm(types::TxNextTerm { nonce: 0 }));
+            assert!(MynaChainModule::go(Origin::NONE, tx).is_err());
+            assert_eq!(MynaChainModule::term_number(), 0);
+        });
+    }
+
+    #[test]
+    fn pool_tags_order_txs_by_nonce() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card(1);
+
+            let created = validate(alice.create_account()).unwrap();
+            assert_eq!(created.provides, vec![(alice.id(), 0u64).encode()]);
+            assert!(created.requires.is_empty());
+
+            register(&alice);
+            register(&bob);
+
+            let current = validate(send(&alice, bob.id(), 1)).unwrap();
+            assert_eq!(current.provides, vec![(alice.id(), 0u64).encode()]);
+            assert!(current.requires.is_empty());
+
+            let future = alice.sign(types::Tx::Send(types::TxSend {
+                to: bob.id(),
+                amount: 1,
+                nonce: 2,
+            }));
+            let future = validate(future).unwrap();
+            assert_eq!(future.provides, vec![(alice.id(), 2u64).encode()]);
+            assert_eq!(future.requires, vec![(alice.id(), 1u64).encode()]);
+        });
+    }
+
+    #[test]
+    fn signatures_do_not_carry_over_to_other_chains() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let tbs = alice.create_account().tbs;
+            let other_chain = types::SigningContext {
+                genesis_hash: H256::repeat_byte(0xff),
+                ..MynaChainModule::signing_context()
+            };
+            let tx = types::SignedData {
+                signature: alice.sign_digest(other_chain.sighash(&tbs).as_bytes()),
+                tbs,
+                id: alice.id(),
+            };
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, tx),
+                Error::<Test>::BadSignature
+            );
+        });
+    }
+
+    #[test]
+    fn revoked_card_cannot_create_account() {
+        new_test_ext().execute_with(|| {
+            // card(n) has serial n + 1
+            assert_ok!(MynaChainModule::update_crl(Origin::ROOT, TEST_CA.crl(&[1], "210101000000Z")));
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, card(0).create_account()),
+                Error::<Test>::CertificateRevoked
+            );
+            register(&card(1));
+        });
+    }
+
+    #[test]
+    fn revoked_account_is_frozen() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card(1);
+            register(&alice);
+            register(&bob);
+            fund(&alice, 10);
+
+            assert_ok!(MynaChainModule::update_crl(Origin::ROOT, TEST_CA.crl(&[1], "210101000000Z")));
+            let test_ca = Blake2Hasher::hash(&TEST_CA.cert[..]);
+            assert!(MynaChainModule::is_revoked(test_ca, vec![1]));
+            assert_eq!(
+                MynaChainModule::account_status(alice.id()),
+                Some(types::AccountStatus::Revoked)
+            );
+            assert_eq!(
+                MynaChainModule::account_status(bob.id()),
+                Some(types::AccountStatus::Active)
+            );
+
+            let tx = send(&alice, bob.id(), 5);
+            assert_noop!(MynaChainModule::go(Origin::NONE, tx.clone()), Error::<Test>::AccountRevoked);
+            assert_eq!(
+                validate(tx),
+                InvalidTransaction::Custom(ACCOUNT_REVOKED).into()
+            );
+        });
+    }
+
+    #[test]
+    fn crl_must_come_from_a_trusted_ca() {
+        new_test_ext().execute_with(|| {
+            assert_noop!(
+                MynaChainModule::update_crl(Origin::ROOT, ROGUE_CA.crl(&[1], "210101000000Z")),
+                Error::<Test>::UntrustedCrl
+            );
+            let mut forged = TEST_CA.crl(&[1], "210101000000Z");
+            let last = forged.len() - 1;
+            forged[last] ^= 1;
+            assert_noop!(
+                MynaChainModule::update_crl(Origin::ROOT, forged),
+                Error::<Test>::UntrustedCrl
+            );
+            assert!(MynaChainModule::update_crl(Origin::ROOT, vec![1, 2, 3]).is_err());
+        });
+    }
+
+    #[test]
+    fn oversized_crls_are_rejected() {
+        new_test_ext().execute_with(|| {
+            let mut crl = TEST_CA.crl(&[1], "210101000000Z");
+            crl.resize(weights::MAX_CRL_LEN + 1, 0);
+            assert_noop!(MynaChainModule::update_crl(Origin::ROOT, crl), Error::<Test>::CrlTooLarge);
+        });
+    }
+
+    #[test]
+    fn only_root_or_oracle_updates_crl() {
+        new_test_ext().execute_with(|| {
+            let crl = TEST_CA.crl(&[1], "210101000000Z");
+            assert_noop!(
+                MynaChainModule::update_crl(Origin::signed(7), crl.clone()),
+                Error::<Test>::NotCrlOracle
+            );
+            assert!(MynaChainModule::set_crl_oracle(Origin::signed(7), Some(7)).is_err());
+            assert_ok!(MynaChainModule::set_crl_oracle(Origin::ROOT, Some(7)));
+            assert_ok!(MynaChainModule::update_crl(Origin::signed(7), crl));
+
+            assert_ok!(MynaChainModule::set_crl_oracle(Origin::ROOT, None));
+            assert_noop!(
+                MynaChainModule::update_crl(Origin::signed(7), TEST_CA.crl(&[2], "210102000000Z")),
+                Error::<Test>::NotCrlOracle
+            );
+        });
+    }
+
+    const CRL_URL: &str = "http://localhost:8080/crl";
+
+    /// Serve `crl` at `CRL_URL` for the `n`th request
+    fn serve_crl(state: &Arc<RwLock<OffchainState>>, n: u16, crl: Vec<u8>) {
+        state.write().expect_request(
+            n,
+            PendingRequest {
+                method: "GET".into(),
+                uri: CRL_URL.into(),
+                response: Some(crl),
+                sent: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    fn run_offchain_worker() {
+        <MynaChainModule as OffchainWorker<u64>>::offchain_worker(CrlFetchInterval::get());
+    }
+
+    #[test]
+    fn offchain_worker_submits_fetched_crl() {
+        let (mut ext, offchain_state, pool_state) = new_offchain_test_ext();
+        ext.execute_with(|| {
+            let alice = card(0);
+            register(&alice);
+            sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, CRL_URL_KEY, CRL_URL.as_bytes());
+            let crl = TEST_CA.crl(&[1], "210101000000Z");
+            serve_crl(&offchain_state, 0, crl.clone());
+
+            run_offchain_worker();
+
+            let tx = pool_state.write().transactions.pop().unwrap();
+            assert!(pool_state.read().transactions.is_empty());
+            let tx = Extrinsic::decode(&mut &tx[..]).unwrap();
+            assert_eq!(tx.0, None);
+            let mock::Call::MynaChainModule(call) = tx.1;
+            assert_eq!(call, Call::submit_crl(crl.clone()));
+
+            assert!(MynaChainModule::validate_unsigned(&call).is_ok());
+            assert_ok!(MynaChainModule::submit_crl(Origin::NONE, crl));
+            assert_eq!(
+                MynaChainModule::account_status(alice.id()),
+                Some(types::AccountStatus::Revoked)
+            );
+            assert_eq!(
+                MynaChainModule::validate_unsigned(&call),
+                InvalidTransaction::Stale.into()
+            );
+        });
+    }
+
+    #[test]
+    fn offchain_worker_skips_known_or_untrusted_crls() {
+        let (mut ext, offchain_state, pool_state) = new_offchain_test_ext();
+        ext.execute_with(|| {
+            // no URL configured: nothing is fetched
+            run_offchain_worker();
+
+            sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, CRL_URL_KEY, CRL_URL.as_bytes());
+            serve_crl(&offchain_state, 0, ROGUE_CA.crl(&[1], "210101000000Z"));
+            run_offchain_worker();
+            assert!(pool_state.read().transactions.is_empty());
+
+            let crl = TEST_CA.crl(&[1], "210101000000Z");
+            assert_ok!(MynaChainModule::update_crl(Origin::ROOT, crl.clone()));
+            serve_crl(&offchain_state, 1, crl);
+            run_offchain_worker();
+            assert!(pool_state.read().transactions.is_empty());
+        });
+    }
+
+    #[test]
+    fn offchain_worker_drops_oversized_crls() {
+        let (mut ext, offchain_state, pool_state) = new_offchain_test_ext();
+        ext.execute_with(|| {
+            sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, CRL_URL_KEY, CRL_URL.as_bytes());
+            let mut crl = TEST_CA.crl(&[1], "210101000000Z");
+            crl.resize(2 * weights::MAX_CRL_LEN, 0);
+            serve_crl(&offchain_state, 0, crl);
+
+            assert_eq!(
+                MynaChainModule::fetch_crl().err().map(<&'static str>::from),
+                Some("CrlTooLarge")
+            );
+            assert!(pool_state.read().transactions.is_empty());
+        });
+    }
+
+    #[test]
+    fn unsigned_crl_must_be_signed_by_trusted_ca() {
+        new_test_ext().execute_with(|| {
+            let call = Call::submit_crl(ROGUE_CA.crl(&[1], "210101000000Z"));
+            assert_eq!(
+                MynaChainModule::validate_unsigned(&call),
+                InvalidTransaction::BadProof.into()
+            );
+            assert!(MynaChainModule::submit_crl(Origin::signed(1), TEST_CA.crl(&[1], "210101000000Z")).is_err());
+
+            let mut oversized = TEST_CA.crl(&[1], "210101000000Z");
+            oversized.resize(weights::MAX_CRL_LEN + 1, 0);
+            assert_eq!(
+                MynaChainModule::validate_unsigned(&Call::submit_crl(oversized)),
+                InvalidTransaction::ExhaustsResources.into()
+            );
+        });
+    }
+
+    #[test]
+    fn expired_account_migrates_to_renewed_card() {
+        new_test_ext().execute_with(|| {
+            let old = card_with_validity(0, ("200101000000Z", "210630235959Z"));
+            let renewed = card(1);
+            let bob = card(2);
+            let id = register(&old);
+            register(&bob);
+            fund(&old, 50);
+            assert_ok!(MynaChainModule::go(
+                Origin::NONE,
+                old.sign(types::Tx::Write(types::TxWrite {
+                    data: vec![7],
+                    nonce: 1,
+                }))
+            ));
+
+            Timestamp::set_timestamp(1_625_097_600_000); // 2021-07-01, old card expired
+            let tx = old.migrate(id, &renewed, 2);
+            assert_ok!(validate(tx.clone()));
+            assert_ok!(MynaChainModule::go(Origin::NONE, tx));
+
+            let account = MynaChainModule::account(id);
+            assert_eq!(account.fingerprint, MynaChainModule::fingerprint(&renewed.cert));
+            assert_eq!(account.data, vec![7]);
+            assert_eq!(account.nonce, 3);
+            assert_eq!(MynaChainModule::compute_balance(id), Ok(50));
+            assert_eq!(MynaChainModule::account_status(id), Some(types::AccountStatus::Active));
+            assert_eq!(MynaChainModule::migrated_cert(renewed.id()), Some(id));
+
+            let send = renewed.sign_for(
+                id,
+                types::Tx::Send(types::TxSend {
+                    to: bob.id(),
+                    amount: 20,
+                    nonce: 3,
+                }),
+            );
+            assert_ok!(MynaChainModule::go(Origin::NONE, send));
+            assert_eq!(MynaChainModule::compute_balance(bob.id()), Ok(20));
+
+            // the old card no longer speaks for the account
+            let stale = old.sign_for(
+                id,
+                types::Tx::Write(types::TxWrite {
+                    data: vec![],
+                    nonce: 4,
+                }),
+            );
+            assert_noop!(MynaChainModule::go(Origin::NONE, stale), Error::<Test>::BadSignature);
+        });
+    }
+
+    #[test]
+    fn renewed_cert_cannot_be_reused() {
+        new_test_ext().execute_with(|| {
+            let old = card(0);
+            let renewed = card(1);
+            let id = register(&old);
+            assert_ok!(MynaChainModule::go(Origin::NONE, old.migrate(id, &renewed, 0)));
+            assert_eq!(validate(renewed.create_account()), InvalidTransaction::Stale.into());
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, renewed.create_account()),
+                Error::<Test>::CertificateInUse
+            );
+
+            let other = card(2);
+            let other_id = register(&other);
+            assert_err!(
+                MynaChainModule::go(Origin::NONE, other.migrate(other_id, &renewed, 0)),
+                Error::<Test>::CertificateInUse
+            );
+            // a card that already has an account of its own can't take over another one
+            assert_err!(
+                MynaChainModule::go(Origin::NONE, renewed.migrate(id, &other, 1)),
+                Error::<Test>::CertificateInUse
+            );
+        });
+    }
+
+    #[test]
+    fn migration_needs_both_cards() {
+        new_test_ext().execute_with(|| {
+            let old = card(0);
+            let renewed = card(1);
+            let id = register(&old);
+
+            let mut tx = old.migrate(id, &renewed, 0);
+            if let types::Tx::MigrateAccount(tbs) = &mut tx.tbs {
+                tbs.new_signature = old.sign_digest(
+                    MynaChainModule::signing_context().sighash(&tbs.unsigned()).as_bytes(),
+                );
+            }
+            let tx = old.sign_for(id, tx.tbs);
+            assert_eq!(validate(tx.clone()), InvalidTransaction::BadProof.into());
+            assert_err!(MynaChainModule::go(Origin::NONE, tx), Error::<Test>::BadSignature);
+
+            let hijack = renewed.migrate(id, &renewed, 1);
+            assert_noop!(MynaChainModule::go(Origin::NONE, hijack), Error::<Test>::BadSignature);
+
+            let rogue = rogue_card(1);
+            assert_err!(
+                MynaChainModule::go(Origin::NONE, old.migrate(id, &rogue, 1)),
+                Error::<Test>::UnknownCa
+            );
+        });
+    }
+
+    #[test]
+    fn revoked_account_cannot_migrate() {
+        new_test_ext().execute_with(|| {
+            let old = card(0);
+            let id = register(&old);
+            assert_ok!(MynaChainModule::update_crl(Origin::ROOT, TEST_CA.crl(&[1], "210101000000Z")));
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, old.migrate(id, &card(1), 0)),
+                Error::<Test>::AccountRevoked
+            );
+        });
+    }
+
+    #[test]
+    fn fingerprints_are_salted_per_chain() {
+        new_test_ext().execute_with(|| {
+            let cert = card(0).cert;
+            assert_ne!(MynaChainModule::fingerprint(&cert), Blake2Hasher::hash(&cert[..]));
+            assert_eq!(MynaChainModule::storage_version(), STORAGE_VERSION);
+            assert_eq!(register(&card(0)), MynaChainModule::fingerprint(&cert));
+        });
+    }
+
+    #[test]
+    fn certs_of_legacy_accounts_stay_in_use() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let bob = card(1);
+            assert_ok!(MynaChainModule::insert_account(dummy_account(alice.cert.clone())));
+
+            assert_eq!(validate(alice.create_account()), InvalidTransaction::Stale.into());
+            assert_noop!(
+                MynaChainModule::go(Origin::NONE, alice.create_account()),
+                Error::<Test>::CertificateInUse
+            );
+
+            // nor can an account move to it
+            let id = register(&bob);
+            assert_err!(
+                MynaChainModule::go(Origin::NONE, bob.migrate(id, &alice, 0)),
+                Error::<Test>::CertificateInUse
+            );
+            assert_eq!(MynaChainModule::account(id).fingerprint, MynaChainModule::fingerprint(&bob.cert));
+        });
+    }
+
+    /// Overwrite the account of `card` with `Account { cert, id, nonce, data, created_at }`,
+    /// as chains from before storage versions hold it
+    fn put_baseline_account(card: &Card, nonce: types::Nonce, data: Vec<u8>) {
+        let baseline = (card.cert.clone(), card.id(), nonce, data, 0 as types::TermNumber).encode();
+        unhashed::put_raw(&Accounts::hashed_key_for(card.id()), &baseline);
+        StorageVersion::put(0);
+    }
+
+    #[test]
+    fn baseline_accounts_expire_with_their_cert() {
+        new_test_ext().execute_with(|| {
+            let alice = card_with_validity(0, ("200101000000Z", "210630235959Z"));
+            register(&alice);
+            put_baseline_account(&alice, 0, vec![]);
+            run_to_block(1);
+            assert_eq!(
+                MynaChainModule::account_status(alice.id()),
+                Some(types::AccountStatus::Active)
+            );
+
+            Timestamp::set_timestamp(1_625_097_600_000); // 2021-07-01
+            assert_eq!(
+                MynaChainModule::account_status(alice.id()),
+                Some(types::AccountStatus::AccountExpired)
+            );
+        });
+    }
+
+    #[test]
+    fn baseline_accounts_are_frozen_by_crls() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            register(&alice);
+            put_baseline_account(&alice, 0, vec![]);
+            run_to_block(1);
+
+            assert_ok!(MynaChainModule::update_crl(Origin::ROOT, TEST_CA.crl(&[1], "210101000000Z")));
+            assert_eq!(
+                MynaChainModule::account_status(alice.id()),
+                Some(types::AccountStatus::Revoked)
+            );
+        });
+    }
+
+    #[test]
+    fn accounts_with_certs_are_migrated() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let id = register(&alice);
+            let info = x509::parse_cert(&alice.cert).unwrap();
+            put_baseline_account(&alice, 4, vec![1, 2]);
+
+            run_to_block(1);
+            assert_eq!(MynaChainModule::storage_version(), STORAGE_VERSION);
+            let account = MynaChainModule::account(id);
+            assert_eq!(account.pubkey, alice.public_key());
+            assert_eq!(account.fingerprint, MynaChainModule::fingerprint(&alice.cert));
+            assert_eq!(account.nonce, 4);
+            assert_eq!(account.data, vec![1, 2]);
+            assert_eq!(account.created_at, 0);
+            assert_eq!(account.expires_at, info.not_after);
+            assert_eq!(account.issuer, Blake2Hasher::hash(&TEST_CA.cert[..]));
+            assert_eq!(account.serial, info.serial.to_vec());
+
+            // a second run must not read the new layout as the old one
+            MynaChainModule::migrate_storage();
+            assert_eq!(MynaChainModule::account(id), account);
+
+            let tx = alice.sign(types::Tx::Write(types::TxWrite {
+                data: vec![3],
+                nonce: 4,
+            }));
+            assert_ok!(MynaChainModule::go(Origin::NONE, tx));
+        });
+    }
+
+    #[test]
+    fn trusted_cas_are_seeded_on_upgrade() {
+        new_test_ext().execute_with(|| {
+            StorageVersion::put(0);
+            run_to_block(1);
+            assert_eq!(MynaChainModule::storage_version(), STORAGE_VERSION);
+            for cert in certs::auth_ca.iter() {
+                let ca = MynaChainModule::trusted_ca(Blake2Hasher::hash(cert));
+                assert_eq!(ca.cert, cert.to_vec());
+                assert_eq!(ca.active_from, 0);
+            }
+            let test_ca = MynaChainModule::trusted_ca(Blake2Hasher::hash(&TEST_CA.cert[..]));
+            assert_eq!(test_ca.cert, TEST_CA.cert);
+        });
+    }
+
+    #[test]
+    fn accounts_with_der_keys_are_migrated() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            let id = register(&alice);
+            let account = MynaChainModule::account(id);
+            let old = types::AccountV1 {
+                pubkey: x509::parse_cert(&alice.cert).unwrap().public_key.to_vec(),
+                fingerprint: account.fingerprint,
+                id,
+                nonce: account.nonce,
+                data: account.data.clone(),
+                created_at: account.created_at,
+                expires_at: account.expires_at,
+                issuer: account.issuer,
+                serial: account.serial.clone(),
+            };
+            unhashed::put(&Accounts::hashed_key_for(id), &old);
+            StorageVersion::put(1);
+
+            run_to_block(1);
+            assert_eq!(MynaChainModule::storage_version(), STORAGE_VERSION);
+            assert_eq!(MynaChainModule::account(id), account);
+        });
+    }
+
+    #[test]
+    fn accounts_are_migrated_in_batches() {
+        new_test_ext().execute_with(|| {
+            let alice = card(0);
+            register(&alice);
+            for i in 0..MIGRATION_BATCH {
+                let account = dummy_account(i.encode());
+                RawBalance::insert(account.id, 1);
+                assert_ok!(MynaChainModule::insert_account(account));
+            }
+            StorageVersion::put(2);
+            let write = |nonce| alice.sign(types::Tx::Write(types::TxWrite { data: vec![], nonce }));
+
+            run_to_block(1);
+            assert_eq!(MynaChainModule::storage_version(), 2);
+            assert_eq!(MynaChainModule::migration_cursor(), MIGRATION_BATCH);
+            assert_eq!(validate(write(0)), UnknownTransaction::CannotLookup.into());
+            assert_noop!(MynaChainModule::go(Origin::NONE, write(0)), Error::<Test>::MigrationPending);
+
+            run_to_block(2);
+            assert_eq!(MynaChainModule::storage_version(), STORAGE_VERSION);
+            assert_eq!(MynaChainModule::migration_cursor(), 0);
+            assert_eq!(MynaChainModule::total_issuance(), MIGRATION_BATCH as types::Balance);
+            assert_ok!(MynaChainModule::go(Origin::NONE, write(0)));
+        });
+    }
+
+    #[test]
+    fn raw_balances_are_migrated_to_ledgers() {
+        new_test_ext().execute_with(|| {
+            let alice = account_in_term(1);
+            assert_ok!(MynaChainModule::force_next_term(Origin::ROOT));
+            let bob = account_in_term(2);
+            CumulativeVotes::insert(1, 70);
+            // alice was minted 10 and sent on the 70 she was distributed
+            RawBalance::insert(alice, -60);
+            RawBalance::insert(bob, 80);
+            StorageVersion::put(2);
+
+            run_to_block(1);
+            assert_eq!(MynaChainModule::storage_version(), STORAGE_VERSION);
+            assert_eq!(MynaChainModule::ledger(alice), types::Ledger { free: 10, claimed: 70 });
+            assert_eq!(MynaChainModule::ledger(bob), types::Ledger { free: 80, claimed: 0 });
+            assert!(!RawBalance::exists(alice));
+            assert_eq!(MynaChainModule::total_issuance(), 90);
+            assert_eq!(MynaChainModule::eligible_accounts(), 1);
+
+            assert_ok!(MynaChainModule::force_next_term(Origin::ROOT));
+            assert_ok!(MynaChainModule::cast_vote(alice, 5));
+            assert_eq!(MynaChainModule::compute_balance(alice), Ok(15));
+            assert_eq!(MynaChainModule::compute_balance(bob), Ok(85));
+            assert_eq!(MynaChainModule::total_issuance(), 100);
+        });
+    }
 }
@@ -1,18 +1,109 @@
-use crate::certs;
+use crate::x509;
 use frame_support::dispatch::{Decode, Encode, Vec};
-use myna::crypto;
-use rsa::RSAPublicKey;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 use sp_core::{Blake2Hasher, Hasher, H256};
 pub type AccountId = H256;
 pub type Signature = Vec<u8>;
 pub type Nonce = u64;
 pub type Balance = i128;
 pub type TermNumber = u32;
+/// Hash of a CA's DER cert
+pub type CaId = H256;
 
-/// The struct of individual account
+/// Prefix of every signing payload, so a card signature made for another purpose is never a valid tx
+pub const SIGNING_DOMAIN: &[u8] = b"mynachain/tx/v1";
+
+/// The struct of individual account.
+/// The cert itself is checked at creation and never stored.
 #[derive(Encode, Decode, Default, Clone, PartialEq)]
-#[cfg_attr(feature = "std", derive(Debug))]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
 pub struct Account {
+    /// Public key of the card, decoded once at creation
+    pub pubkey: RsaPublicKey,
+    /// `Blake2(genesis_hash ++ cert)`: proves which cert an account was made with
+    /// to whoever holds the cert, and to nobody else
+    pub fingerprint: H256,
+    /// The fingerprint of the cert the account was created with. Accounts created
+    /// before ids were salted keep `Blake2(cert)`, which anyone holding the cert can match.
+    pub id: AccountId,
+    pub nonce: Nonce,
+    pub data: Vec<u8>,
+    pub created_at: TermNumber,
+    /// notAfter of the cert, in unix seconds
+    pub expires_at: u64,
+    /// CA that issued the cert
+    pub issuer: CaId,
+    /// Serial number of the cert, as DER INTEGER content
+    pub serial: Vec<u8>,
+}
+
+/// Why a signature or a cert didn't check out
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum VerifyError {
+    /// The DER public key is malformed
+    InvalidPublicKey,
+    /// The signature doesn't match the key and payload
+    BadSignature,
+    /// None of the given CAs signed the cert
+    UnknownCa,
+}
+
+/// RSA public key as big-endian integers without leading zeros
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub struct RsaPublicKey {
+    pub modulus: Vec<u8>,
+    pub exponent: Vec<u8>,
+}
+
+impl RsaPublicKey {
+    /// From a DER RSAPublicKey, as found in certs
+    pub fn from_der(der: &[u8]) -> Result<Self, VerifyError> {
+        let (modulus, exponent) =
+            x509::parse_rsa_public_key(der).map_err(|_| VerifyError::InvalidPublicKey)?;
+        let strip = |int: &[u8]| -> Vec<u8> {
+            int.iter().cloned().skip_while(|b| *b == 0).collect()
+        };
+        Ok(RsaPublicKey {
+            modulus: strip(modulus),
+            exponent: strip(exponent),
+        })
+    }
+
+    /// PKCS#1 v1.5 over a SHA-256 `DigestInfo`, as the card signs. Runs natively on the node.
+    pub fn verify(&self, digest: &[u8], signature: &[u8]) -> Result<(), VerifyError> {
+        if myna_crypto::myna_crypto::rsa_verify_pkcs1_sha256(
+            &self.modulus[..],
+            &self.exponent[..],
+            digest,
+            signature,
+        ) {
+            Ok(())
+        } else {
+            Err(VerifyError::BadSignature)
+        }
+    }
+}
+
+/// `Account` as stored in storage version 1, with the DER public key
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+pub struct AccountV1 {
+    pub pubkey: Vec<u8>,
+    pub fingerprint: H256,
+    pub id: AccountId,
+    pub nonce: Nonce,
+    pub data: Vec<u8>,
+    pub created_at: TermNumber,
+    pub expires_at: u64,
+    pub issuer: CaId,
+    pub serial: Vec<u8>,
+}
+
+/// `Account` as stored in storage version 0, with the full cert and nothing derived from it
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+pub struct AccountWithCert {
     pub cert: Vec<u8>,
     pub id: AccountId,
     pub nonce: Nonce,
@@ -20,13 +111,26 @@ pub struct Account {
     pub created_at: TermNumber,
 }
 
+/// Whether an account can still sign txs
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub enum AccountStatus {
+    Active,
+    /// The cert is past notAfter; the holder must migrate to a renewed card
+    AccountExpired,
+    /// The cert is on its CA's revocation list; the account is frozen
+    Revoked,
+}
+
 #[derive(Encode, Decode, Default, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct SignedData {
     pub tbs: Tx,
     pub signature: Signature,
     pub id: AccountId,
 }
 #[derive(Encode, Decode, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum Tx {
     CreateAccount(TxCreateAccount),
     Send(TxSend),
@@ -34,6 +138,10 @@ pub enum Tx {
     Vote(TxVote),
     Write(TxWrite),
     NextTerm(TxNextTerm),
+    MigrateAccount(TxMigrateAccount),
+    Withdraw(TxWithdraw),
+    LinkKey(TxLinkKey),
+    UnlinkKey(TxUnlinkKey),
     Other,
 }
 impl Default for Tx {
@@ -41,59 +149,237 @@ impl Default for Tx {
         Tx::Other
     }
 }
-impl SignedData {
-    pub fn verify(&self, pubkey: RSAPublicKey) -> Result<(), &'static str> {
-        let encoded = self.tbs.encode();
-        let sighash = Blake2Hasher::hash(&encoded);
-
-        match crypto::verify(pubkey, sighash.as_ref(), &self.signature[..]) {
-            Ok(()) => return Ok(()),
-            Err(_) => return Err("Verification failed"),
+impl Tx {
+    /// The nonce the signer committed to, if this kind of tx carries one
+    pub fn nonce(&self) -> Option<Nonce> {
+        match self {
+            Tx::CreateAccount(t) => Some(t.nonce),
+            Tx::Send(t) => Some(t.nonce),
+            Tx::Mint(t) => Some(t.nonce),
+            Tx::Vote(t) => Some(t.nonce),
+            Tx::Write(t) => Some(t.nonce),
+            Tx::NextTerm(t) => Some(t.nonce),
+            Tx::MigrateAccount(t) => Some(t.nonce),
+            Tx::Withdraw(t) => Some(t.nonce),
+            Tx::LinkKey(t) => Some(t.nonce),
+            Tx::UnlinkKey(t) => Some(t.nonce),
+            Tx::Other => None,
         }
     }
 }
+/// The chain and runtime a signature is bound to
+#[derive(Encode, Decode, Default, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct SigningContext {
+    pub genesis_hash: H256,
+    pub spec_version: u32,
+}
+
+impl SigningContext {
+    /// The exact bytes a wallet hashes and signs:
+    /// `SIGNING_DOMAIN ++ genesis_hash ++ spec_version (LE) ++ tbs.encode()`
+    pub fn payload(&self, tbs: &Tx) -> Vec<u8> {
+        let mut payload = SIGNING_DOMAIN.to_vec();
+        payload.extend_from_slice(self.genesis_hash.as_bytes());
+        payload.extend_from_slice(&self.spec_version.to_le_bytes());
+        payload.extend_from_slice(&tbs.encode());
+        payload
+    }
+    /// The digest handed to the card
+    pub fn sighash(&self, tbs: &Tx) -> H256 {
+        Blake2Hasher::hash(&self.payload(tbs))
+    }
+    /// `Blake2(genesis_hash ++ cert)`, the id of an account created with `cert`.
+    /// Doesn't depend on `spec_version`, so it survives runtime upgrades.
+    pub fn fingerprint(&self, cert: &[u8]) -> H256 {
+        Blake2Hasher::hash(&[self.genesis_hash.as_bytes(), cert].concat())
+    }
+}
+
+impl SignedData {
+    pub fn verify(&self, pubkey: &RsaPublicKey, ctx: &SigningContext) -> Result<(), VerifyError> {
+        let sighash = ctx.sighash(&self.tbs);
+        pubkey.verify(sighash.as_ref(), &self.signature[..])
+    }
+}
 
 #[derive(Encode, Decode, Default, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct TxCreateAccount {
     pub cert: Vec<u8>,
     pub nonce: Nonce,
 }
 
 impl TxCreateAccount {
-    pub fn check_ca(&self) -> Result<(), &'static str> {
-        for ca in certs::auth_ca.iter() {
-            if crypto::verify_cert(&self.cert[..], ca).is_ok() {
-                return Ok(());
-            }
-        }
-        return Err("Failed to check CA");
+    /// The cert must be issued by one of `cas`: named as its issuer, and whose key signed it.
+    /// Returns the id of the issuer.
+    pub fn check_ca(&self, cas: &[Vec<u8>]) -> Result<CaId, VerifyError> {
+        let cert = x509::parse_cert(&self.cert[..]).map_err(|_| VerifyError::UnknownCa)?;
+        let digest = sp_io::hashing::sha2_256(cert.tbs);
+        cas.iter()
+            .find(|ca| {
+                x509::parse_cert(&ca[..]).map_or(false, |ca| {
+                    ca.subject == cert.issuer
+                        && RsaPublicKey::from_der(ca.public_key)
+                            .and_then(|key| key.verify(&digest[..], cert.signature))
+                            .is_ok()
+                })
+            })
+            .map(|ca| Blake2Hasher::hash(&ca[..]))
+            .ok_or(VerifyError::UnknownCa)
     }
 }
 
 #[derive(Encode, Decode, Default, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct TxSend {
     pub to: AccountId,
     pub amount: Balance,
     pub nonce: Nonce,
 }
 
+/// A trusted CA and the window in which it may vouch for new accounts
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct CaRecord<BlockNumber> {
+    pub cert: Vec<u8>,
+    pub active_from: BlockNumber,
+    pub retired_at: Option<BlockNumber>,
+}
+
+impl<BlockNumber: PartialOrd> CaRecord<BlockNumber> {
+    pub fn is_active(&self, now: &BlockNumber) -> bool {
+        self.active_from <= *now && self.retired_at.as_ref().map_or(true, |at| now < at)
+    }
+}
+
+/// An account allowed to mint
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Issuer {
+    pub cap_per_term: Balance,
+}
+
+/// Funds of an account. `free` holds everything up to the point where
+/// `CumulativeVotes` stood at `claimed`; distribution since then accrues on top
+/// until the next settlement folds it in.
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Ledger {
+    /// Never negative
+    pub free: Balance,
+    pub claimed: Balance,
+}
+
 #[derive(Encode, Decode, Default, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct TxMint {
+    pub to: AccountId,
     pub amount: Balance,
     pub nonce: Nonce,
 }
 #[derive(Encode, Decode, Default, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct TxVote {
     pub amount: Balance,
     pub nonce: Nonce,
 }
 #[derive(Encode, Decode, Default, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct TxWrite {
     pub data: Vec<u8>,
     pub nonce: Nonce,
 }
 #[derive(Encode, Decode, Default, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct TxNextTerm {
     pub nonce: Nonce,
 }
 
+/// Move funds out to an account of the Balances pallet
+#[derive(Encode, Decode, Default, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct TxWithdraw {
+    /// SCALE-encoded `AccountId` of the runtime, e.g. an sr25519 public key
+    pub to: Vec<u8>,
+    pub amount: Balance,
+    pub nonce: Nonce,
+}
+
+/// What a linked key may do for its account
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum KeyScope {
+    /// Send up to `limit` in total, and write the account's data
+    Spend { limit: Balance },
+    /// Write the account's data, nothing else
+    WriteOnly,
+}
+
+impl Default for KeyScope {
+    fn default() -> Self {
+        KeyScope::WriteOnly
+    }
+}
+
+/// An sr25519 key allowed to act for an account, so that not every action needs the card
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct LinkedKey {
+    pub account: AccountId,
+    pub scope: KeyScope,
+    /// Sent through the key so far, counted against `KeyScope::Spend::limit`
+    pub spent: Balance,
+    /// Last term the key may be used in
+    pub expires_at: TermNumber,
+}
+
+/// Offer to let the runtime account `key` act for the signer, once `key` accepts
+/// with `accept_key`. Relinking a key already linked to the signer replaces its
+/// scope and expiry and resets what it spent.
+#[derive(Encode, Decode, Default, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct TxLinkKey {
+    /// SCALE-encoded `AccountId` of the runtime, e.g. an sr25519 public key
+    pub key: Vec<u8>,
+    pub scope: KeyScope,
+    pub expires_at: TermNumber,
+    pub nonce: Nonce,
+}
+
+/// Revoke a key linked by `TxLinkKey` and withdraw any offer to it
+#[derive(Encode, Decode, Default, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct TxUnlinkKey {
+    pub key: Vec<u8>,
+    pub nonce: Nonce,
+}
+
+/// Move an account to a renewed card. Signed by the old card as usual,
+/// and by the new one over `unsigned()`.
+#[derive(Encode, Decode, Default, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct TxMigrateAccount {
+    pub new_cert: Vec<u8>,
+    pub new_signature: Signature,
+    pub nonce: Nonce,
+}
+
+impl TxMigrateAccount {
+    /// What the new card signs: this tx without `new_signature`
+    pub fn unsigned(&self) -> Tx {
+        Tx::MigrateAccount(TxMigrateAccount {
+            new_signature: Vec::new(),
+            ..self.clone()
+        })
+    }
+
+    pub fn verify_new_signature(
+        &self,
+        pubkey: &RsaPublicKey,
+        ctx: &SigningContext,
+    ) -> Result<(), VerifyError> {
+        let sighash = ctx.sighash(&self.unsigned());
+        pubkey.verify(sighash.as_ref(), &self.new_signature[..])
+    }
+}
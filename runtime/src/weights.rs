@@ -0,0 +1,194 @@
+//! Weights of mynachain txs.
+//!
+//! A unit of weight is a microsecond of execution, so `MaximumBlockWeight` of the
+//! runtime is one second per block. `cargo bench -p node-template-runtime --features
+//! test-utils --bench weights` measures each step the constants below charge for.
+//! They are estimates that haven't been checked against a recorded run yet. Record
+//! the medians next to each constant when they are, and again whenever the
+//! verification path changes.
+
+use crate::types;
+use frame_support::dispatch::Vec;
+use frame_support::weights::{ClassifyDispatch, DispatchClass, PaysFee, WeighData, Weight};
+use sp_runtime::traits::SaturatedConversion;
+
+/// Dispatch, reading and writing the account, nonce and event
+pub const TX_BASE_WEIGHT: Weight = 100;
+/// One RSA-2048 signature check through the `myna_crypto` host function
+pub const RSA_VERIFY_WEIGHT: Weight = 200;
+/// Checking a cert against one trusted CA: parsing both certs and a host-function RSA check
+pub const CA_VERIFY_WEIGHT: Weight = 250;
+/// How many CAs a cert may be checked against, enforced by `add_ca`. JPKI runs two
+/// at a time, more while one is being replaced.
+pub const MAX_ACTIVE_CAS: usize = 4;
+/// Hashing and parsing a byte of a cert
+pub const CERT_BYTE_WEIGHT: Weight = 1;
+/// Storing a byte of `TxWrite::data`
+pub const DATA_BYTE_WEIGHT: Weight = 1;
+/// Crediting an account of the Balances pallet
+pub const CURRENCY_WEIGHT: Weight = 50;
+/// Hashing and parsing a byte of a CRL
+pub const CRL_BYTE_WEIGHT: Weight = 1;
+/// Recording one revoked serial
+pub const CRL_ENTRY_WEIGHT: Weight = 100;
+/// Shortest DER of a revoked cert entry: a one-byte serial and a UTCTime
+pub const MIN_CRL_ENTRY_LEN: usize = 20;
+/// Largest CRL accepted, so that one fits in a block with room to spare
+pub const MAX_CRL_LEN: usize = 64 * 1024;
+
+/// Everything it takes to turn `cert` into an account
+fn cert_weight(cert: &[u8]) -> Weight {
+    (CA_VERIFY_WEIGHT * MAX_ACTIVE_CAS as Weight)
+        .saturating_add(CERT_BYTE_WEIGHT.saturating_mul(cert.len().saturated_into()))
+}
+
+/// Weight of a `go` carrying `tbs`
+pub fn tx_weight(tbs: &types::Tx) -> Weight {
+    let checks = match tbs {
+        types::Tx::CreateAccount(t) => RSA_VERIFY_WEIGHT.saturating_add(cert_weight(&t.cert[..])),
+        // the old card's signature and the new one's
+        types::Tx::MigrateAccount(t) => {
+            (2 * RSA_VERIFY_WEIGHT).saturating_add(cert_weight(&t.new_cert[..]))
+        }
+        types::Tx::Write(t) => RSA_VERIFY_WEIGHT
+            .saturating_add(DATA_BYTE_WEIGHT.saturating_mul(t.data.len().saturated_into())),
+        types::Tx::Send(_)
+        | types::Tx::Mint(_)
+        | types::Tx::Vote(_)
+        | types::Tx::LinkKey(_)
+        | types::Tx::UnlinkKey(_) => RSA_VERIFY_WEIGHT,
+        types::Tx::Withdraw(_) => RSA_VERIFY_WEIGHT.saturating_add(CURRENCY_WEIGHT),
+        types::Tx::NextTerm(_) | types::Tx::Other => 0,
+    };
+    TX_BASE_WEIGHT.saturating_add(checks)
+}
+
+/// Weight of applying a DER CRL of `len` bytes: finding its CA, and the most
+/// entries that many bytes can hold
+pub fn crl_weight(len: usize) -> Weight {
+    let len: Weight = len.saturated_into();
+    let entries = len / MIN_CRL_ENTRY_LEN as Weight;
+    TX_BASE_WEIGHT
+        .saturating_add(CA_VERIFY_WEIGHT * MAX_ACTIVE_CAS as Weight)
+        .saturating_add(CRL_BYTE_WEIGHT.saturating_mul(len))
+        .saturating_add(CRL_ENTRY_WEIGHT.saturating_mul(entries))
+}
+
+/// Weight of `update_crl` and `submit_crl`, by the size of the CRL
+pub struct CrlWeight;
+
+impl<'a> WeighData<(&'a Vec<u8>,)> for CrlWeight {
+    fn weigh_data(&self, (crl,): (&'a Vec<u8>,)) -> Weight {
+        crl_weight(crl.len())
+    }
+}
+
+impl<'a> ClassifyDispatch<(&'a Vec<u8>,)> for CrlWeight {
+    fn classify_dispatch(&self, _: (&'a Vec<u8>,)) -> DispatchClass {
+        DispatchClass::Normal
+    }
+}
+
+/// `update_crl` comes from root or the CRL oracle and `submit_crl` unsigned, so as with
+/// `go` there is no one to charge: the CRL signature, `validate_crl` and `MAX_CRL_LEN`
+/// are what keep them in check
+impl PaysFee for CrlWeight {
+    fn pays_fee(&self) -> bool {
+        false
+    }
+}
+
+/// Weight of `go`, by the kind and size of the tx
+pub struct TxWeight;
+
+impl<'a> WeighData<(&'a types::SignedData,)> for TxWeight {
+    fn weigh_data(&self, (tx,): (&'a types::SignedData,)) -> Weight {
+        tx_weight(&tx.tbs)
+    }
+}
+
+impl<'a> ClassifyDispatch<(&'a types::SignedData,)> for TxWeight {
+    fn classify_dispatch(&self, _: (&'a types::SignedData,)) -> DispatchClass {
+        DispatchClass::Normal
+    }
+}
+
+/// `go` comes in unsigned, so there is no one to charge: `validate_tx` and
+/// `MAX_DATA_LEN` are what keep it in check
+impl PaysFee for TxWeight {
+    fn pays_fee(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use crate::mynachain::Call;
+    use frame_support::weights::GetDispatchInfo;
+
+    /// `MaximumBlockWeight` and `AvailableBlockRatio` of the runtime
+    const NORMAL_BLOCK_WEIGHT: Weight = 750_000;
+
+    fn write(len: usize) -> types::Tx {
+        types::Tx::Write(types::TxWrite {
+            data: vec![0; len],
+            nonce: 0,
+        })
+    }
+
+    #[test]
+    fn go_is_weighed_by_its_tx() {
+        let tx = card(0).create_account();
+        let info = Call::<Test>::go(tx.clone()).get_dispatch_info();
+        assert_eq!(info.weight, tx_weight(&tx.tbs));
+        assert_eq!(info.class, DispatchClass::Normal);
+        assert!(!info.pays_fee);
+    }
+
+    #[test]
+    fn weight_grows_with_cert_and_data() {
+        let small = card(0).create_account().tbs;
+        let mut large = small.clone();
+        if let types::Tx::CreateAccount(t) = &mut large {
+            t.cert.extend(vec![0; 1000]);
+        }
+        assert_eq!(tx_weight(&large), tx_weight(&small) + 1000 * CERT_BYTE_WEIGHT);
+        assert_eq!(tx_weight(&write(1000)), tx_weight(&write(0)) + 1000 * DATA_BYTE_WEIGHT);
+    }
+
+    #[test]
+    fn cert_txs_cost_more_than_transfers() {
+        let send = types::Tx::Send(types::TxSend::default());
+        let create = card(0).create_account().tbs;
+        assert!(tx_weight(&create) > tx_weight(&send) + CA_VERIFY_WEIGHT);
+    }
+
+    #[test]
+    fn a_block_holds_a_bounded_number_of_account_creations() {
+        // JPKI user certs are about 1.5kB
+        let mut create = card(0).create_account().tbs;
+        if let types::Tx::CreateAccount(t) = &mut create {
+            t.cert.resize(1500, 0);
+        }
+        let per_block = NORMAL_BLOCK_WEIGHT / tx_weight(&create);
+        assert!(per_block > 0);
+        assert!(per_block < 500);
+    }
+
+    #[test]
+    fn crls_are_weighed_by_size() {
+        let crl = vec![0u8; 1000];
+        let info = Call::<Test>::update_crl(crl.clone()).get_dispatch_info();
+        assert_eq!(info.weight, crl_weight(crl.len()));
+        assert!(!info.pays_fee);
+        assert!(crl_weight(2000) > crl_weight(1000) + 1000 * CRL_BYTE_WEIGHT);
+        assert!(crl_weight(MAX_CRL_LEN) < NORMAL_BLOCK_WEIGHT);
+    }
+
+    #[test]
+    fn oversized_writes_never_fit_in_a_block() {
+        assert!(tx_weight(&write(NORMAL_BLOCK_WEIGHT as usize)) > NORMAL_BLOCK_WEIGHT);
+    }
+}
@@ -0,0 +1,304 @@
+//! Just enough DER/X.509 reading for the runtime's checks: validity period, serial
+//! number, issuer, public key, the signed part of certs and CRLs.
+
+use sp_std::vec::Vec;
+
+const SEQUENCE: u8 = 0x30;
+const INTEGER: u8 = 0x02;
+const BIT_STRING: u8 = 0x03;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+const VERSION_TAG: u8 = 0xa0;
+
+/// Split one TLV off the front of `input`: (tag, content, rest)
+pub fn read_tlv(input: &[u8]) -> Result<(u8, &[u8], &[u8]), &'static str> {
+    let tag = *input.get(0).ok_or("DER: unexpected end")?;
+    let first = *input.get(1).ok_or("DER: unexpected end")?;
+    let (len, header) = if first < 0x80 {
+        (first as usize, 2)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 {
+            return Err("DER: unsupported length");
+        }
+        let bytes = input.get(2..2 + n).ok_or("DER: unexpected end")?;
+        let len = bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (len, 2 + n)
+    };
+    let end = header.checked_add(len).ok_or("DER: bad length")?;
+    let content = input.get(header..end).ok_or("DER: unexpected end")?;
+    Ok((tag, content, &input[end..]))
+}
+
+/// Like `read_tlv`, but the tag must be `expected`: (content, rest)
+pub fn expect_tlv(input: &[u8], expected: u8) -> Result<(&[u8], &[u8]), &'static str> {
+    let (tag, content, rest) = read_tlv(input)?;
+    if tag != expected {
+        return Err("DER: unexpected tag");
+    }
+    Ok((content, rest))
+}
+
+/// Seconds since the unix epoch, from a UTCTime or GeneralizedTime in `YYMMDDHHMMSSZ` form
+pub fn parse_time(tag: u8, time: &[u8]) -> Result<u64, &'static str> {
+    let digits = |range: core::ops::Range<usize>| -> Result<u64, &'static str> {
+        time.get(range)
+            .ok_or("bad time")?
+            .iter()
+            .try_fold(0u64, |acc, c| match c {
+                b'0'..=b'9' => Ok(acc * 10 + (c - b'0') as u64),
+                _ => Err("bad time"),
+            })
+    };
+    let (year, rest) = match tag {
+        UTC_TIME => {
+            let yy = digits(0..2)?;
+            (if yy >= 50 { 1900 + yy } else { 2000 + yy }, 2)
+        }
+        GENERALIZED_TIME => (digits(0..4)?, 4),
+        _ => return Err("bad time"),
+    };
+    if time.len() != rest + 11 || time[rest + 10] != b'Z' {
+        return Err("bad time");
+    }
+    let month = digits(rest..rest + 2)?;
+    let day = digits(rest + 2..rest + 4)?;
+    let hour = digits(rest + 4..rest + 6)?;
+    let minute = digits(rest + 6..rest + 8)?;
+    let second = digits(rest + 8..rest + 10)?;
+    // anything before the epoch doesn't fit in u64 seconds
+    if year < 1970 {
+        return Err("bad time");
+    }
+    if month < 1 || month > 12 || day < 1 || day > 31 || hour > 23 || minute > 59 || second > 60 {
+        return Err("bad time");
+    }
+    days_from_civil(year, month, day)
+        .and_then(|days| days.checked_mul(86400))
+        .and_then(|secs| secs.checked_add(hour * 3600 + minute * 60 + second))
+        .ok_or("bad time")
+}
+
+/// Days since 1970-01-01 (Howard Hinnant's algorithm), `None` before it
+fn days_from_civil(year: u64, month: u64, day: u64) -> Option<u64> {
+    let y = if month <= 2 { year.checked_sub(1)? } else { year };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    (era * 146097 + doe).checked_sub(719468)
+}
+
+/// The parts of a certificate the runtime looks at
+#[derive(Debug, PartialEq)]
+pub struct CertInfo<'a> {
+    /// DER of tbsCertificate, the signed part
+    pub tbs: &'a [u8],
+    pub serial: &'a [u8],
+    /// DER of the issuer Name
+    pub issuer: &'a [u8],
+    /// DER of the subject Name
+    pub subject: &'a [u8],
+    pub not_before: u64,
+    pub not_after: u64,
+    /// DER of the PKCS#1 RSAPublicKey inside subjectPublicKeyInfo
+    pub public_key: &'a [u8],
+    pub signature: &'a [u8],
+}
+
+impl<'a> CertInfo<'a> {
+    /// Whether the cert is usable at `now` (unix seconds)
+    pub fn check_validity(&self, now: u64) -> Result<(), &'static str> {
+        if now < self.not_before {
+            return Err("Certificate not yet valid");
+        }
+        if now > self.not_after {
+            return Err("Certificate expired");
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_cert(cert: &[u8]) -> Result<CertInfo<'_>, &'static str> {
+    let (certificate, _) = expect_tlv(cert, SEQUENCE)?;
+    let (tbs_raw, rest) = expect_raw(certificate, SEQUENCE)?;
+    let (_signature_algorithm, rest) = expect_tlv(rest, SEQUENCE)?;
+    let (signature, _) = expect_tlv(rest, BIT_STRING)?;
+    let signature = bit_string_bytes(signature)?;
+
+    let (tbs, _) = expect_tlv(tbs_raw, SEQUENCE)?;
+    let (tag, _, after_version) = read_tlv(tbs)?;
+    let tbs = if tag == VERSION_TAG { after_version } else { tbs };
+    let (serial, rest) = expect_tlv(tbs, INTEGER)?;
+    let (_signature_algorithm, rest) = expect_tlv(rest, SEQUENCE)?;
+    let (issuer, rest) = expect_raw(rest, SEQUENCE)?;
+    let (validity, rest) = expect_tlv(rest, SEQUENCE)?;
+    let (subject, rest) = expect_raw(rest, SEQUENCE)?;
+    let (spki, _) = expect_tlv(rest, SEQUENCE)?;
+    let (_algorithm, rest) = expect_tlv(spki, SEQUENCE)?;
+    let (public_key, _) = expect_tlv(rest, BIT_STRING)?;
+    let public_key = bit_string_bytes(public_key)?;
+
+    let (tag, not_before, rest) = read_tlv(validity)?;
+    let not_before = parse_time(tag, not_before)?;
+    let (tag, not_after, _) = read_tlv(rest)?;
+    let not_after = parse_time(tag, not_after)?;
+
+    Ok(CertInfo {
+        tbs: tbs_raw,
+        serial,
+        issuer,
+        subject,
+        not_before,
+        not_after,
+        public_key,
+        signature,
+    })
+}
+
+/// Modulus and public exponent of a DER RSAPublicKey, big-endian
+pub fn parse_rsa_public_key(der: &[u8]) -> Result<(&[u8], &[u8]), &'static str> {
+    let (key, _) = expect_tlv(der, SEQUENCE)?;
+    let (modulus, rest) = expect_tlv(key, INTEGER)?;
+    let (exponent, _) = expect_tlv(rest, INTEGER)?;
+    Ok((modulus, exponent))
+}
+
+/// Content of a BIT STRING made of whole bytes
+fn bit_string_bytes(content: &[u8]) -> Result<&[u8], &'static str> {
+    // the first byte counts unused bits
+    match content.split_first() {
+        Some((0, bytes)) => Ok(bytes),
+        _ => Err("DER: bad bit string"),
+    }
+}
+
+/// Like `expect_tlv`, but keeps the header: (whole TLV, rest)
+fn expect_raw(input: &[u8], expected: u8) -> Result<(&[u8], &[u8]), &'static str> {
+    let (_, rest) = expect_tlv(input, expected)?;
+    Ok((&input[..input.len() - rest.len()], rest))
+}
+
+/// The parts of a CRL the runtime looks at
+#[derive(Debug, PartialEq)]
+pub struct CrlInfo<'a> {
+    /// DER of tbsCertList, the signed part
+    pub tbs: &'a [u8],
+    /// DER of the issuer Name
+    pub issuer: &'a [u8],
+    pub this_update: u64,
+    pub next_update: Option<u64>,
+    /// Serial numbers of the revoked certs
+    pub revoked: Vec<&'a [u8]>,
+    pub signature: &'a [u8],
+}
+
+pub fn parse_crl(crl: &[u8]) -> Result<CrlInfo<'_>, &'static str> {
+    let (certificate_list, _) = expect_tlv(crl, SEQUENCE)?;
+    let (tbs, rest) = expect_raw(certificate_list, SEQUENCE)?;
+    let (_signature_algorithm, rest) = expect_tlv(rest, SEQUENCE)?;
+    let (signature, _) = expect_tlv(rest, BIT_STRING)?;
+    let signature = bit_string_bytes(signature)?;
+
+    let (tbs_content, _) = expect_tlv(tbs, SEQUENCE)?;
+    let (tag, _, after_version) = read_tlv(tbs_content)?;
+    let rest = if tag == INTEGER { after_version } else { tbs_content };
+    let (_signature_algorithm, rest) = expect_tlv(rest, SEQUENCE)?;
+    let (issuer, rest) = expect_raw(rest, SEQUENCE)?;
+    let (tag, this_update, mut rest) = read_tlv(rest)?;
+    let this_update = parse_time(tag, this_update)?;
+
+    let mut next_update = None;
+    if let Ok((tag, time, after)) = read_tlv(rest) {
+        if tag == UTC_TIME || tag == GENERALIZED_TIME {
+            next_update = Some(parse_time(tag, time)?);
+            rest = after;
+        }
+    }
+
+    let mut revoked = Vec::new();
+    if let Ok((mut entries, _)) = expect_tlv(rest, SEQUENCE) {
+        while !entries.is_empty() {
+            let (entry, next) = expect_tlv(entries, SEQUENCE)?;
+            let (serial, _) = expect_tlv(entry, INTEGER)?;
+            revoked.push(serial);
+            entries = next;
+        }
+    }
+
+    Ok(CrlInfo {
+        tbs,
+        issuer,
+        this_update,
+        next_update,
+        revoked,
+        signature,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::der;
+    use crate::mock::{card_with_validity, TEST_CA};
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(parse_time(UTC_TIME, b"700101000000Z"), Ok(0));
+        assert_eq!(parse_time(UTC_TIME, b"251019145959Z"), Ok(1760885999));
+        assert_eq!(parse_time(GENERALIZED_TIME, b"20251019145959Z"), Ok(1760885999));
+        assert_eq!(parse_time(UTC_TIME, b"491231235959Z"), Ok(2524607999));
+        assert!(parse_time(UTC_TIME, b"251319145959Z").is_err());
+        assert!(parse_time(UTC_TIME, b"2510191459Z").is_err());
+        // valid encodings of times before the epoch
+        assert!(parse_time(UTC_TIME, b"500101000000Z").is_err());
+        assert!(parse_time(UTC_TIME, b"691231235959Z").is_err());
+        assert!(parse_time(GENERALIZED_TIME, b"00000101000000Z").is_err());
+        assert!(parse_time(GENERALIZED_TIME, b"19691231235959Z").is_err());
+        assert_eq!(parse_time(GENERALIZED_TIME, b"99991231235959Z"), Ok(253402300799));
+    }
+
+    #[test]
+    fn reads_cert_fields() {
+        let card = card_with_validity(7, ("200101000000Z", "251231235959Z"));
+        let info = parse_cert(&card.cert).unwrap();
+        assert_eq!(info.serial, &[8u8][..]);
+        assert_eq!(info.issuer, &der::name("Mynachain Test CA")[..]);
+        assert_eq!(info.subject, &der::name("")[..]);
+        let (modulus, exponent) = parse_rsa_public_key(info.public_key).unwrap();
+        assert_eq!(modulus.len(), 129);
+        assert_eq!(exponent, &[1, 0, 1][..]);
+        assert_eq!(info.not_before, 1577836800);
+        assert_eq!(info.not_after, 1767225599);
+
+        assert_eq!(info.check_validity(1577836799), Err("Certificate not yet valid"));
+        assert_eq!(info.check_validity(1700000000), Ok(()));
+        assert_eq!(info.check_validity(1767225600), Err("Certificate expired"));
+        assert_eq!(parse_cert(&TEST_CA.cert).unwrap().subject, &der::name("Mynachain Test CA")[..]);
+    }
+
+    #[test]
+    fn reads_crl_fields() {
+        let crl = TEST_CA.crl(&[3, 200], "210101000000Z");
+        let info = parse_crl(&crl).unwrap();
+        assert_eq!(info.issuer, &der::name("Mynachain Test CA")[..]);
+        assert_eq!(info.this_update, 1609459200);
+        assert_eq!(info.next_update, None);
+        assert_eq!(info.revoked, vec![&[3u8][..], &[0, 200][..]]);
+        assert_eq!(info.signature.len(), 128);
+
+        let empty = parse_crl(&TEST_CA.crl(&[], "210101000000Z")).unwrap();
+        assert!(empty.revoked.is_empty());
+        assert!(parse_crl(&TEST_CA.cert).is_err());
+    }
+
+    #[test]
+    fn reads_jpki_roots() {
+        for ca in crate::certs::auth_ca.iter() {
+            assert!(parse_cert(ca).is_ok());
+        }
+        let first = parse_cert(crate::certs::auth_ca[0]).unwrap();
+        assert_eq!(first.not_after, 1760885999);
+    }
+}
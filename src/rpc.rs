@@ -0,0 +1,231 @@
+//! `mynachain_*` RPC methods. Thin wrappers over the `MynachainApi` runtime API,
+//! plus submission of bare `SignedData`.
+
+use std::sync::Arc;
+
+use codec::{Decode, Encode};
+use futures::TryFutureExt;
+use jsonrpc_core::{futures::Future, Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use node_template_runtime::{
+	mynachain, opaque::Block, types, Call, MynachainApi as MynachainRuntimeApi, UncheckedExtrinsic,
+};
+use serde::Deserialize;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::Bytes;
+use sp_runtime::{
+	generic::BlockId,
+	traits::Block as BlockT,
+	transaction_validity::{InvalidTransaction, UnknownTransaction},
+};
+use sp_transaction_pool::{error::IntoPoolError, TransactionPool};
+
+/// Error code for failures inside the runtime call.
+const RUNTIME_ERROR: i64 = 1;
+/// Error code for txs the pool refused.
+const POOL_ERROR: i64 = 2;
+
+/// Future's type for jsonrpc
+pub type FutureResult<T> = Box<dyn Future<Item = T, Error = RpcError> + Send>;
+
+/// A `SignedData` as sent by clients: either hex SCALE or plain JSON.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum SignedDataInput {
+	/// SCALE-encoded `SignedData`, as printed by `myna-tx`.
+	Scale(Bytes),
+	/// `SignedData` spelled out field by field.
+	Json(types::SignedData),
+}
+
+impl SignedDataInput {
+	fn into_signed_data(self) -> Result<types::SignedData> {
+		match self {
+			SignedDataInput::Scale(bytes) => types::SignedData::decode(&mut &bytes[..])
+				.map_err(|e| RpcError {
+					code: ErrorCode::InvalidParams,
+					message: "Invalid SCALE-encoded SignedData".into(),
+					data: Some(format!("{:?}", e).into()),
+				}),
+			SignedDataInput::Json(tx) => Ok(tx),
+		}
+	}
+}
+
+/// Mynachain RPC methods.
+#[rpc]
+pub trait MynachainApi<BlockHash> {
+	/// Balance of `id` including accrued distribution.
+	#[rpc(name = "mynachain_balance")]
+	fn balance(&self, id: types::AccountId, at: Option<BlockHash>) -> Result<Option<types::Balance>>;
+
+	/// Nonce the next tx of `id` must carry.
+	#[rpc(name = "mynachain_nonce")]
+	fn nonce(&self, id: types::AccountId, at: Option<BlockHash>) -> Result<Option<types::Nonce>>;
+
+	/// Stored account record of `id`.
+	#[rpc(name = "mynachain_account")]
+	fn account(&self, id: types::AccountId, at: Option<BlockHash>) -> Result<Option<types::Account>>;
+
+	/// `AccountExpired` means the holder has to move to a renewed card,
+	/// `Revoked` that the card is on its CA's revocation list.
+	#[rpc(name = "mynachain_accountStatus")]
+	fn account_status(&self, id: types::AccountId, at: Option<BlockHash>) -> Result<Option<types::AccountStatus>>;
+
+	/// Current term.
+	#[rpc(name = "mynachain_termNumber")]
+	fn term_number(&self, at: Option<BlockHash>) -> Result<types::TermNumber>;
+
+	/// Cumulative votes as of `term`.
+	#[rpc(name = "mynachain_votes")]
+	fn votes(&self, term: types::TermNumber, at: Option<BlockHash>) -> Result<types::Balance>;
+
+	/// What `id` voted in `term`, if it did.
+	#[rpc(name = "mynachain_voteOf")]
+	fn vote_of(
+		&self,
+		term: types::TermNumber,
+		id: types::AccountId,
+		at: Option<BlockHash>,
+	) -> Result<Option<types::Balance>>;
+
+	/// Sum of all balances.
+	#[rpc(name = "mynachain_totalIssuance")]
+	fn total_issuance(&self, at: Option<BlockHash>) -> Result<types::Balance>;
+
+	/// Wrap a card-signed `SignedData` into an extrinsic and submit it to the pool.
+	/// Returns the tx hash.
+	#[rpc(name = "mynachain_submit")]
+	fn submit(&self, tx: SignedDataInput) -> FutureResult<BlockHash>;
+}
+
+/// Implements `MynachainApi` on top of a client and a transaction pool.
+pub struct Mynachain<C, P> {
+	client: Arc<C>,
+	pool: Arc<P>,
+}
+
+impl<C, P> Mynachain<C, P> {
+	/// Create new `Mynachain` with the given reference to the client and pool.
+	pub fn new(client: Arc<C>, pool: Arc<P>) -> Self {
+		Mynachain { client, pool }
+	}
+}
+
+impl<C, P> Mynachain<C, P> where
+	C: HeaderBackend<Block>,
+{
+	fn at(&self, at: Option<<Block as BlockT>::Hash>) -> BlockId<Block> {
+		BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash))
+	}
+}
+
+fn runtime_error(e: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(RUNTIME_ERROR),
+		message: "Runtime call failed".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+/// Say why the pool refused a tx in terms of mynachain rules.
+fn pool_error(e: sp_transaction_pool::error::Error) -> RpcError {
+	use sp_transaction_pool::error::Error;
+
+	let message = match e {
+		Error::InvalidTransaction(InvalidTransaction::Stale) =>
+			"Nonce already used, or the account already exists".into(),
+		Error::InvalidTransaction(InvalidTransaction::BadProof) =>
+			"Signature or certificate verification failed".into(),
+		Error::InvalidTransaction(InvalidTransaction::Call) =>
+			"This kind of tx can't be submitted".into(),
+		Error::InvalidTransaction(InvalidTransaction::Custom(mynachain::ACCOUNT_EXPIRED)) =>
+			"Account expired: migrate to your renewed card".into(),
+		Error::InvalidTransaction(InvalidTransaction::Custom(mynachain::ACCOUNT_REVOKED)) =>
+			"Account frozen: its certificate was revoked".into(),
+		Error::UnknownTransaction(UnknownTransaction::CannotLookup) =>
+			"Account not found".into(),
+		e => format!("{:?}", e),
+	};
+	RpcError {
+		code: ErrorCode::ServerError(POOL_ERROR),
+		message,
+		data: None,
+	}
+}
+
+impl<C, P> MynachainApi<<Block as BlockT>::Hash> for Mynachain<C, P> where
+	C: ProvideRuntimeApi + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: MynachainRuntimeApi<Block>,
+	P: TransactionPool<Block = Block, Hash = <Block as BlockT>::Hash> + Send + Sync + 'static,
+{
+	fn balance(&self, id: types::AccountId, at: Option<<Block as BlockT>::Hash>) -> Result<Option<types::Balance>> {
+		self.client.runtime_api().balance(&self.at(at), id).map_err(runtime_error)
+	}
+
+	fn nonce(&self, id: types::AccountId, at: Option<<Block as BlockT>::Hash>) -> Result<Option<types::Nonce>> {
+		self.client.runtime_api().nonce(&self.at(at), id).map_err(runtime_error)
+	}
+
+	fn account(&self, id: types::AccountId, at: Option<<Block as BlockT>::Hash>) -> Result<Option<types::Account>> {
+		self.client.runtime_api().account(&self.at(at), id).map_err(runtime_error)
+	}
+
+	fn account_status(&self, id: types::AccountId, at: Option<<Block as BlockT>::Hash>) -> Result<Option<types::AccountStatus>> {
+		self.client.runtime_api().account_status(&self.at(at), id).map_err(runtime_error)
+	}
+
+	fn term_number(&self, at: Option<<Block as BlockT>::Hash>) -> Result<types::TermNumber> {
+		self.client.runtime_api().term_number(&self.at(at)).map_err(runtime_error)
+	}
+
+	fn votes(&self, term: types::TermNumber, at: Option<<Block as BlockT>::Hash>) -> Result<types::Balance> {
+		self.client.runtime_api().votes(&self.at(at), term).map_err(runtime_error)
+	}
+
+	fn vote_of(
+		&self,
+		term: types::TermNumber,
+		id: types::AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<types::Balance>> {
+		self.client.runtime_api().vote_of(&self.at(at), term, id).map_err(runtime_error)
+	}
+
+	fn total_issuance(&self, at: Option<<Block as BlockT>::Hash>) -> Result<types::Balance> {
+		self.client.runtime_api().total_issuance(&self.at(at)).map_err(runtime_error)
+	}
+
+	fn submit(&self, tx: SignedDataInput) -> FutureResult<<Block as BlockT>::Hash> {
+		let tx = match tx.into_signed_data() {
+			Ok(tx) => tx,
+			Err(e) => return Box::new(jsonrpc_core::futures::future::err(e)),
+		};
+		let call = Call::MynaChainModule(mynachain::Call::go(tx));
+		let xt = UncheckedExtrinsic::new_unsigned(call).encode();
+		let xt = match Decode::decode(&mut &xt[..]) {
+			Ok(xt) => xt,
+			Err(e) => return Box::new(jsonrpc_core::futures::future::err(runtime_error(e))),
+		};
+
+		let at = self.at(None);
+		Box::new(
+			Box::pin(self.pool.submit_one(&at, xt))
+				.compat()
+				.map_err(|e| e.into_pool_error().map(pool_error).unwrap_or_else(runtime_error)),
+		)
+	}
+}
+
+/// All the RPC extensions of this node.
+pub fn create<C, P, M>(client: Arc<C>, pool: Arc<P>) -> jsonrpc_core::IoHandler<M> where
+	C: ProvideRuntimeApi + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: MynachainRuntimeApi<Block>,
+	P: TransactionPool<Block = Block, Hash = <Block as BlockT>::Hash> + Send + Sync + 'static,
+	M: jsonrpc_core::Metadata + Default,
+{
+	let mut io = jsonrpc_core::IoHandler::default();
+	io.extend_with(MynachainApi::to_delegate(Mynachain::new(client, pool)));
+	io
+}
@@ -18,6 +18,7 @@ native_executor_instance!(
 	pub Executor,
 	node_template_runtime::api::dispatch,
 	node_template_runtime::native_version,
+	myna_crypto::myna_crypto::HostFunctions,
 );
 
 construct_simple_protocol! {
@@ -47,6 +48,11 @@ macro_rules! new_full_start {
 				let maintainable_pool = sp_transaction_pool::MaintainableTransactionPool::new(pool, maintainer);
 				Ok(maintainable_pool)
 			})?
+			.with_rpc_extensions(|client, pool, _backend, _fetcher, _remote_blockchain|
+				-> Result<jsonrpc_core::IoHandler<sc_rpc::Metadata>, _>
+			{
+				Ok(crate::rpc::create(client, pool))
+			})?
 			.with_import_queue(|_config, client, mut select_chain, transaction_pool| {
 				let select_chain = select_chain.take()
 					.ok_or_else(|| sc_service::Error::SelectChainRequired)?;
@@ -0,0 +1,53 @@
+//! The RSA check must give the same answer natively (host function) and inside the
+//! Wasm runtime (`fallback`), or native and Wasm block execution would diverge.
+
+use codec::{Decode, Encode};
+use node_template_runtime::WASM_BINARY;
+use rand::{rngs::StdRng, SeedableRng};
+use rsa::{hash::Hashes, PaddingScheme, PublicKey, RSAPrivateKey};
+use sc_executor::{call_in_wasm, WasmExecutionMethod};
+use sp_io::TestExternalities;
+
+fn wasm(modulus: &[u8], exponent: &[u8], digest: &[u8], signature: &[u8]) -> bool {
+    let mut ext = TestExternalities::default();
+    let mut ext = ext.ext();
+    let res = call_in_wasm::<_, (sp_io::SubstrateHostFunctions, myna_crypto::myna_crypto::HostFunctions)>(
+        "MynaCryptoTestApi_rsa_verify_pkcs1_sha256_fallback",
+        &(modulus.to_vec(), exponent.to_vec(), digest.to_vec(), signature.to_vec()).encode(),
+        WasmExecutionMethod::Interpreted,
+        &mut ext,
+        &WASM_BINARY,
+        8,
+    )
+    .unwrap();
+    bool::decode(&mut &res[..]).unwrap()
+}
+
+/// Native and Wasm results, which must agree
+fn verify(modulus: &[u8], exponent: &[u8], digest: &[u8], signature: &[u8]) -> bool {
+    let native = myna_crypto::myna_crypto::rsa_verify_pkcs1_sha256(modulus, exponent, digest, signature);
+    assert_eq!(native, wasm(modulus, exponent, digest, signature));
+    native
+}
+
+#[test]
+fn native_and_wasm_agree() {
+    let key = RSAPrivateKey::new(&mut StdRng::seed_from_u64(0), 2048).unwrap();
+    let n = key.n().to_bytes_be();
+    let e = key.e().to_bytes_be();
+    let digest = [7u8; 32];
+    let signature = key
+        .sign(PaddingScheme::PKCS1v15, Some(&Hashes::SHA2_256), &digest)
+        .unwrap();
+
+    assert!(verify(&n, &e, &digest, &signature));
+    assert!(!verify(&n, &e, &[8u8; 32], &signature));
+    assert!(!verify(&n, &e, &digest[..31], &signature));
+    assert!(!verify(&n, &e, &digest, &signature[1..]));
+    assert!(!verify(&n, &[3], &digest, &signature));
+    assert!(!verify(&[], &e, &digest, &signature));
+
+    let mut flipped = signature.clone();
+    flipped[100] ^= 1;
+    assert!(!verify(&n, &e, &digest, &flipped));
+}